@@ -0,0 +1,66 @@
+//! Method-heavy scripts, which spend most of their time looking names up.
+//! Run with `cargo bench`.
+#![feature(test)]
+
+extern crate test;
+
+use rlox::{
+    Backend,
+    Interpreter,
+};
+use test::Bencher;
+
+const METHODS: &str = "
+class Counter {
+    init() { this.count = 0; }
+    add(n) {
+        this.count = this.count + n;
+        return this;
+    }
+}
+class Doubler < Counter {
+    add(n) { return super.add(n * 2); }
+}
+var counter = Doubler();
+for (var i = 0; i < 10000; i = i + 1) {
+    counter.add(1).add(i);
+}
+";
+
+const LOCALS: &str = "
+fun sum(n) {
+    var total = 0;
+    for (var i = 0; i < n; i = i + 1) {
+        var square = i * i;
+        total = total + square;
+    }
+    return total;
+}
+sum(20000);
+";
+
+fn bench(b: &mut Bencher, backend: Backend, source: &str) {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    b.iter(|| interp.eval(source).unwrap());
+}
+
+#[bench]
+fn methods_tree_walk(b: &mut Bencher) {
+    bench(b, Backend::TreeWalk, METHODS);
+}
+
+#[bench]
+fn methods_bytecode(b: &mut Bencher) {
+    bench(b, Backend::Bytecode, METHODS);
+}
+
+#[bench]
+fn locals_tree_walk(b: &mut Bencher) {
+    bench(b, Backend::TreeWalk, LOCALS);
+}
+
+#[bench]
+fn locals_bytecode(b: &mut Bencher) {
+    bench(b, Backend::Bytecode, LOCALS);
+}
@@ -1,16 +1,70 @@
-use std::env;
+use std::{
+    env,
+    process,
+    thread,
+};
+
+use rlox::{
+    Backend,
+    Lints,
+};
+
+/// Stack for the thread scripts run on. The tree-walker recurses on it for
+/// every call, so it needs room for `rlox::DEFAULT_MAX_DEPTH` of them even in
+/// debug builds.
+const STACK_SIZE: usize = 256 << 20;
+
+/// The exit code for scripts that stop with an error.
+const FAILURE: i32 = 1;
+
+/// The exit code for arguments `rlox` doesn't understand.
+const USAGE_ERROR: i32 = 2;
+
+const USAGE: &str = "Usage: rlox [--bytecode] [--allow=<lint>]... [script]";
 
 fn main() {
-    let mut args = env::args();
-    let res = if args.len() > 2 {
-        println!("Usage: rlox [script]");
-        return;
-    } else if args.len() == 2 {
-        rlox::run_file(args.nth(1).unwrap())
-    } else {
-        rlox::run_prompt()
-    };
-    if let Err(err) = res {
-        println!("Error: {}", err);
+    let mut backend = Backend::TreeWalk;
+    let mut lints = Lints::default();
+    let mut script = None;
+    for arg in env::args().skip(1) {
+        if arg == "--bytecode" {
+            backend = Backend::Bytecode;
+        } else if let Some(name) = arg.strip_prefix("--allow=") {
+            match name.parse() {
+                Ok(lint) => {
+                    lints.allow(lint);
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(USAGE_ERROR);
+                },
+            }
+        } else if script.is_none() && !arg.starts_with("--") {
+            script = Some(arg);
+        } else {
+            eprintln!("{}", USAGE);
+            process::exit(USAGE_ERROR);
+        }
+    }
+    let res = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || match script {
+            Some(script) => rlox::run_file(backend, lints, script),
+            None => rlox::run_prompt(backend, lints),
+        })
+        .map(|handle| handle.join().unwrap_or_else(|_| process::exit(101)));
+    match res {
+        Ok(Ok(())) => {},
+        Ok(Err(err)) => {
+            if let Some(code) = rlox::exit_code(&err) {
+                process::exit(code);
+            }
+            eprintln!("{}", err);
+            process::exit(FAILURE);
+        },
+        Err(err) => {
+            eprintln!("could not start interpreter: {}", err);
+            process::exit(FAILURE);
+        },
     }
 }
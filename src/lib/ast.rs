@@ -4,18 +4,49 @@ use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Assign(Token, Rc<Expr>, Option<usize>),
+    Assign(Token, Rc<Expr>, Option<Slot>),
     Binary(Rc<Expr>, Token, Rc<Expr>),
-    Call(Rc<Expr>, Token, Vec<Expr>),
+    /// The callee, closing paren and arguments, and for calls of a property
+    /// the cache of the method they invoke.
+    Call(Rc<Expr>, Token, Vec<Expr>, MethodCache),
     Get(Rc<Expr>, Token),
-    Grouping(Rc<Expr>),
-    Literal(Primitive),
+    Grouping(Rc<Expr>, Span),
+    Index(Rc<Expr>, Token, Rc<Expr>),
+    List(Vec<Expr>, Span),
+    Literal(Primitive, Span),
     Logical(Rc<Expr>, Token, Rc<Expr>),
+    Map(Vec<(Expr, Expr)>, Span),
     Set(Rc<Expr>, Token, Rc<Expr>),
-    Super(Token, Token, Option<usize>),
-    This(Token, Option<usize>),
+    SetIndex(Rc<Expr>, Token, Rc<Expr>, Rc<Expr>),
+    Super(Token, Token, Option<Slot>),
+    This(Token, Option<Slot>),
     Unary(Token, Rc<Expr>),
-    Variable(Token, Option<usize>),
+    Variable(Token, Option<Slot>),
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign(name, value, _) => name.span.to(value.span()),
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                left.span().to(right.span())
+            },
+            Expr::Call(callee, paren, ..) => callee.span().to(paren.span),
+            Expr::Get(object, name) => object.span().to(name.span),
+            Expr::Grouping(_, span)
+            | Expr::List(_, span)
+            | Expr::Literal(_, span)
+            | Expr::Map(_, span) => *span,
+            Expr::Index(object, bracket, _) => object.span().to(bracket.span),
+            Expr::Set(object, _, value) | Expr::SetIndex(object, _, _, value) => {
+                object.span().to(value.span())
+            },
+            Expr::Super(kw, method, _) => kw.span.to(method.span),
+            Expr::This(kw, _) => kw.span,
+            Expr::Unary(op, right) => op.span.to(right.span()),
+            Expr::Variable(name, _) => name.span,
+        }
+    }
 }
 
 pub trait Visitor<T> {
@@ -37,13 +68,44 @@ where
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Block(Vec<Stmt>),
+    Block(Vec<Stmt>, Span),
+    Break(Token),
     Class(Token, Option<Expr>, Vec<Stmt>),
+    Continue(Token),
     Expr(Expr),
     Function(Token, Vec<Token>, Vec<Stmt>),
-    If(Expr, Rc<Stmt>, Option<Rc<Stmt>>),
-    Print(Expr),
+    If(Expr, Rc<Stmt>, Option<Rc<Stmt>>, Span),
+    /// `import` keyword, path string and the name the module is bound to.
+    Import(Token, Token, Token),
+    Print(Expr, Span),
     Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    /// `try` keyword, body, the `catch` clause's variable and body, and the
+    /// `finally` body. At least one of the clauses is present.
+    Try(Token, Vec<Stmt>, Option<(Token, Vec<Stmt>)>, Option<Vec<Stmt>>),
     Var(Token, Expr),
-    While(Expr, Rc<Stmt>),
+    /// A loop's condition, body and, for desugared `for` loops, the
+    /// increment, which also runs when the body `continue`s.
+    While(Expr, Rc<Stmt>, Option<Expr>, Span),
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block(_, span)
+            | Stmt::If(_, _, _, span)
+            | Stmt::Print(_, span)
+            | Stmt::While(_, _, _, span) => *span,
+            Stmt::Break(kw) | Stmt::Continue(kw) => kw.span,
+            Stmt::Class(name, _, _) | Stmt::Function(name, _, _) => name.span,
+            Stmt::Expr(expr) => expr.span(),
+            Stmt::Return(kw, expr) => {
+                expr.as_ref().map(|expr| kw.span.to(expr.span())).unwrap_or(kw.span)
+            },
+            Stmt::Import(kw, _, name) => kw.span.to(name.span),
+            Stmt::Throw(kw, value) => kw.span.to(value.span()),
+            Stmt::Try(kw, ..) => kw.span,
+            Stmt::Var(name, init) => name.span.to(init.span()),
+        }
+    }
 }
@@ -8,6 +8,7 @@ use std::{
         Debug,
         Display,
     },
+    mem,
     rc::Rc,
 };
 
@@ -15,9 +16,16 @@ pub trait Callable: Debug + Display {
     fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, LoxError>;
 
     fn arity(&self) -> usize;
+
+    /// The name shown for this callable in stack traces.
+    fn name(&self) -> &str;
 }
 
-pub struct RustFn<F>(pub usize, pub F);
+pub struct RustFn<F> {
+    pub name:  LoxStr,
+    pub arity: usize,
+    pub f:     F,
+}
 
 impl<F> Display for RustFn<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -35,8 +43,15 @@ impl<F> RustFn<F>
 where
     F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, LoxError>,
 {
-    pub fn new(arity: usize, f: F) -> RustFn<F> {
-        RustFn(arity, f)
+    pub fn new<S>(name: S, arity: usize, f: F) -> RustFn<F>
+    where
+        S: Into<LoxStr>,
+    {
+        RustFn {
+            name: name.into(),
+            arity,
+            f,
+        }
     }
 }
 
@@ -45,11 +60,15 @@ where
     F: Fn(&mut Interpreter, Vec<Value>) -> Result<Value, LoxError>,
 {
     fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, LoxError> {
-        (self.1)(interp, args)
+        (self.f)(interp, args)
     }
 
     fn arity(&self) -> usize {
-        self.0
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        &self.name
     }
 }
 
@@ -66,16 +85,41 @@ where
 pub struct LoxFn {
     fn_static: Rc<FnStatic>,
     closure:   Environment,
+    /// The instance a bound method is called on.
+    this:      Option<LoxInstance>,
 }
 
 #[derive(Debug)]
-struct FnStatic {
+pub struct FnStatic {
     name:    Token,
     params:  Vec<Token>,
-    body:    Vec<Stmt>,
+    body:    FnBody,
     is_init: bool,
 }
 
+/// The executable part of a function: either the resolved AST for the
+/// tree-walking interpreter, or a chunk produced by the bytecode compiler.
+#[derive(Debug)]
+enum FnBody {
+    Ast(Vec<Stmt>),
+    Chunk(Rc<Chunk>),
+}
+
+impl FnStatic {
+    pub fn compiled(name: &Token, params: &[Token], chunk: Chunk, is_init: bool) -> Self {
+        FnStatic {
+            name: name.clone(),
+            params: params.into(),
+            body: FnBody::Chunk(chunk.into()),
+            is_init,
+        }
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
+
 impl Display for LoxFn {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "<fn {}>", self.fn_static.name.lexeme)
@@ -96,51 +140,127 @@ impl LoxFn {
         closure: Environment,
         is_init: bool,
     ) -> Self {
+        allocate(mem::size_of::<LoxFn>());
         LoxFn {
             fn_static: FnStatic {
                 name: name.clone(),
                 params: params.into(),
-                body: body.into(),
+                body: FnBody::Ast(body.into()),
                 is_init,
             }
             .into(),
             closure,
+            this: None,
+        }
+    }
+
+    pub fn from_static(fn_static: Rc<FnStatic>, closure: Environment) -> Self {
+        allocate(mem::size_of::<LoxFn>());
+        LoxFn {
+            fn_static,
+            closure,
+            this: None,
         }
     }
 
     pub fn bind(&self, this: LoxInstance) -> LoxFn {
-        let mut closure = Environment::with_enclosing(&self.closure);
-        closure.define("this", Value::Instance(this));
+        allocate(mem::size_of::<LoxFn>());
         LoxFn {
             fn_static: self.fn_static.clone(),
-            closure,
+            closure:   self.closure.clone(),
+            this:      Some(this),
         }
     }
-}
 
-impl Callable for LoxFn {
-    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, LoxError> {
-        let env = Environment::with_enclosing(&self.closure);
-        let res = interp.with_env(env, |interp| {
-            for (i, decl_param) in self.fn_static.params.iter().enumerate() {
-                interp.define(decl_param, args[i].clone());
-            }
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        self.closure.trace(tracer);
+        if let Some(this) = &self.this {
+            this.trace(tracer);
+        }
+    }
+
+    /// The compiled body of this function, if it was produced by the
+    /// bytecode compiler.
+    pub fn chunk(&self) -> Option<&Rc<Chunk>> {
+        match &self.fn_static.body {
+            FnBody::Chunk(chunk) => Some(chunk),
+            FnBody::Ast(_) => None,
+        }
+    }
 
-            interp.execute_block(&self.fn_static.body)
-        });
+    /// Creates the environment for a call to this function with the
+    /// parameters bound to `args`. Methods get `this` in the first slot,
+    /// ahead of the parameters: the instance they're bound to, or, if they're
+    /// invoked without being bound first, the one they're called on.
+    pub fn call_env(&self, this: Option<LoxInstance>, args: Vec<Value>) -> Environment {
+        let mut env = Environment::with_enclosing(&self.closure);
+        if let Some(this) = this.or_else(|| self.this.clone()) {
+            env.define(Symbol::THIS, Value::Instance(this));
+        }
+        for (decl_param, arg) in self.fn_static.params.iter().zip(args) {
+            env.define(decl_param.symbol, arg);
+        }
+        env
+    }
 
+    /// Initializers always evaluate to the bound instance, regardless of what
+    /// the body returned. `env` is the environment the call ran in.
+    pub fn init_result(&self, env: &Environment) -> Option<Value> {
         if self.fn_static.is_init {
-            if let Some(this) = self.closure.get_at("this".as_bytes(), 0) {
-                return Ok(this);
-            }
+            env.get_at(Symbol::THIS, Some(Slot {
+                depth: 0,
+                index: 0,
+            }))
+        } else {
+            None
+        }
+    }
+
+    /// Calls this method on `this` without binding it first.
+    pub fn call_method(
+        &self,
+        interp: &mut Interpreter,
+        this: LoxInstance,
+        args: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        self.call_with(interp, Some(this), args)
+    }
+
+    fn call_with(
+        &self,
+        interp: &mut Interpreter,
+        this: Option<LoxInstance>,
+        args: Vec<Value>,
+    ) -> Result<Value, LoxError> {
+        let env = self.call_env(this, args);
+        let res = interp.with_env(env.clone(), |interp| match &self.fn_static.body {
+            FnBody::Ast(body) => interp.execute_block(body).map(|flow| match flow {
+                Some(Flow::Return(value)) => Some(value),
+                _ => None,
+            }),
+            FnBody::Chunk(chunk) => Vm::new().run(interp, chunk.clone()).map(Some),
+        })?;
+
+        if let Some(this) = self.init_result(&env) {
+            return Ok(this);
         }
 
-        res.map(|opt| opt.unwrap_or_else(|| Primitive::Nil.into()))
+        Ok(res.unwrap_or_else(|| Primitive::Nil.into()))
+    }
+}
+
+impl Callable for LoxFn {
+    fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, LoxError> {
+        self.call_with(interp, None, args)
     }
 
     fn arity(&self) -> usize {
         self.fn_static.params.len()
     }
+
+    fn name(&self) -> &str {
+        &self.fn_static.name.lexeme
+    }
 }
 
 impl<T, U> Callable for Either<T, U>
@@ -161,6 +281,13 @@ where
             Either::Left(c) => c.arity(),
         }
     }
+
+    fn name(&self) -> &str {
+        match self {
+            Either::Right(c) => c.name(),
+            Either::Left(c) => c.name(),
+        }
+    }
 }
 
 impl<T> Callable for &T
@@ -174,6 +301,10 @@ where
     fn arity(&self) -> usize {
         (*self).arity()
     }
+
+    fn name(&self) -> &str {
+        (*self).name()
+    }
 }
 
 impl Callable for Rc<dyn Callable> {
@@ -184,6 +315,10 @@ impl Callable for Rc<dyn Callable> {
     fn arity(&self) -> usize {
         (**self).arity()
     }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
 }
 
 impl<T> Callable for Rc<T>
@@ -197,4 +332,8 @@ where
     fn arity(&self) -> usize {
         (**self).arity()
     }
+
+    fn name(&self) -> &str {
+        (**self).name()
+    }
 }
@@ -1,9 +1,10 @@
 use std::{
-    borrow::Borrow,
-    collections::HashMap,
+    cell::RefCell,
     fmt,
-    hash::Hash,
-    rc::Rc,
+    rc::{
+        Rc,
+        Weak,
+    },
 };
 
 use crate::*;
@@ -12,14 +13,15 @@ use crate::*;
 pub struct LoxClass {
     pub name:       LoxStr,
     pub superclass: Option<Rc<LoxClass>>,
-    pub methods:    HashMap<LoxStr, Rc<LoxFn>>,
+    pub methods:    SymbolMap<Rc<LoxFn>>,
+    native:         Option<NativeMembers>,
 }
 
 impl LoxClass {
     pub fn new<S>(
         name: S,
         superclass: Option<Rc<LoxClass>>,
-        methods: HashMap<LoxStr, Rc<LoxFn>>,
+        methods: SymbolMap<Rc<LoxFn>>,
     ) -> Self
     where
         S: Into<LoxStr>,
@@ -28,19 +30,100 @@ impl LoxClass {
             name: name.into(),
             superclass,
             methods,
+            native: None,
         }
     }
 
-    pub fn find_method<K>(&self, instance: &LoxInstance, name: &K) -> Option<Rc<LoxFn>>
+    /// A class defined by the host, whose instances wrap a Rust value.
+    pub(crate) fn native<S>(name: S, members: NativeMembers) -> Self
     where
-        K: Eq + Hash,
-        LoxStr: Borrow<K>,
+        S: Into<LoxStr>,
     {
-        self.methods
-            .get(name)
-            .map(|method| Rc::new(method.bind(instance.clone())))
-            .or_else(|| self.superclass.as_ref().and_then(|sc| sc.find_method(instance, name)))
+        LoxClass {
+            name:       name.into(),
+            superclass: None,
+            methods:    SymbolMap::default(),
+            native:     Some(members),
+        }
+    }
+
+    /// Looks up the method `name`, defined in Lox or natively, bound to
+    /// `instance`.
+    pub fn find_method<S>(&self, instance: &LoxInstance, name: S) -> Option<Value>
+    where
+        S: Into<Symbol>,
+    {
+        let name = name.into();
+        if let Some(method) = self.methods.get(&name) {
+            return Some(Value::LoxFn(Rc::new(method.bind(instance.clone()))));
+        }
+        if let Some(method) = self.native.as_ref().and_then(|native| native.methods.get(&name)) {
+            return Some(method.bind(instance));
+        }
+        self.superclass.as_ref().and_then(|sc| sc.find_method(instance, name))
     }
+
+    /// Looks up the method `name` defined in Lox, unbound. `None` if there's
+    /// no such method or a native method of the same name overrides it.
+    pub(crate) fn lox_method(&self, name: Symbol) -> Option<Rc<LoxFn>> {
+        if let Some(method) = self.methods.get(&name) {
+            return Some(method.clone());
+        }
+        if self.native.as_ref().is_some_and(|native| native.methods.contains_key(&name)) {
+            return None;
+        }
+        self.superclass.as_ref().and_then(|sc| sc.lox_method(name))
+    }
+
+    /// The arity of the initializer instances of this class are constructed
+    /// with, which may be inherited.
+    fn init_arity(&self) -> usize {
+        if let Some(init) = self.methods.get(&Symbol::INIT) {
+            return init.arity();
+        }
+        if let Some(init) = self.native.as_ref().and_then(|n| n.methods.get(&Symbol::INIT)) {
+            return init.arity;
+        }
+        self.superclass.as_ref().map(|sc| sc.init_arity()).unwrap_or(0)
+    }
+
+    /// Whether this is `class` or inherits from it.
+    pub fn is_subclass_of(&self, class: &LoxClass) -> bool {
+        std::ptr::eq(self, class)
+            || self.superclass.as_ref().is_some_and(|sc| sc.is_subclass_of(class))
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        if let Some(superclass) = &self.superclass {
+            tracer.class(superclass);
+        }
+        for method in self.methods.values() {
+            tracer.function(method);
+        }
+    }
+
+    /// Walks the class hierarchy for the first native class, which holds the
+    /// property accessors and formatting for the value instances wrap.
+    pub(crate) fn native_members(&self) -> Option<&NativeMembers> {
+        self.native
+            .as_ref()
+            .or_else(|| self.superclass.as_ref().and_then(|sc| sc.native_members()))
+    }
+}
+
+/// The `isInstance` native: whether `value` is an instance of `class` or one
+/// of its subclasses.
+pub fn is_instance(value: Value, class: Value) -> Result<bool, String> {
+    let class = match class {
+        Value::Class(class) => class,
+        _ => {
+            return Err(format!(
+                "isInstance() expects a class for argument 2, got {}",
+                class.type_name()
+            ))
+        },
+    };
+    Ok(matches!(value, Value::Instance(instance) if instance.is_instance_of(&class)))
 }
 
 impl fmt::Display for LoxClass {
@@ -52,13 +135,42 @@ impl fmt::Display for LoxClass {
 impl Callable for Rc<LoxClass> {
     fn call(&self, interp: &mut Interpreter, args: Vec<Value>) -> Result<Value, LoxError> {
         let instance = LoxInstance::new(self.clone());
-        if let Some(init) = self.methods.get("init".as_bytes()) {
-            init.bind(instance.clone()).call(interp, args)?;
+        if let Some(init) = self.lox_method(Symbol::INIT) {
+            init.call_method(interp, instance.clone(), args)?;
+        } else if let Some(init) = self.find_method(&instance, Symbol::INIT) {
+            init.callable()?.call(interp, args)?;
         }
         Ok(Value::Instance(instance).into())
     }
 
     fn arity(&self) -> usize {
-        self.methods.get("init".as_bytes()).map(|init| init.arity()).unwrap_or(0)
+        self.init_arity()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The method a call site invoked last and the class it was found in, so
+/// that invoking it on another instance of that class skips the lookup. Both
+/// are held weakly: the cache doesn't keep classes alive, and a class's
+/// methods live as long as it does.
+#[derive(Debug, Clone, Default)]
+pub struct MethodCache {
+    entry: RefCell<Option<(Weak<LoxClass>, Weak<LoxFn>)>>,
+}
+
+impl MethodCache {
+    /// Looks up the Lox method `name` of `class`, as `LoxClass::lox_method`.
+    pub fn get(&self, class: &Rc<LoxClass>, name: Symbol) -> Option<Rc<LoxFn>> {
+        if let Some((cached, method)) = &*self.entry.borrow() {
+            if Weak::as_ptr(cached) == Rc::as_ptr(class) {
+                return method.upgrade();
+            }
+        }
+        let method = class.lox_method(name)?;
+        *self.entry.borrow_mut() = Some((Rc::downgrade(class), Rc::downgrade(&method)));
+        Some(method)
     }
 }
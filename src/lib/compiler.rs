@@ -0,0 +1,543 @@
+use crate::*;
+
+use std::rc::Rc;
+
+/// A single bytecode instruction. Operands index into the owning `Chunk`'s
+/// tables or, for jumps, into its `code`.
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Constant(usize),
+    Nil,
+    True,
+    False,
+    Pop,
+    Define(usize),
+    GetVar(usize, Option<Slot>),
+    SetVar(usize, Option<Slot>),
+    GetProperty(usize),
+    SetProperty(usize),
+    GetSuper(usize, Option<Slot>),
+    GetIndex,
+    SetIndex,
+    List(usize),
+    Map(usize),
+    Binary(TokenType),
+    Unary(TokenType),
+    Print,
+    Jump(usize),
+    JumpIfFalse(usize),
+    PushScope,
+    PopScope,
+    Call(usize),
+    /// Replaces the receiver on top of the stack with what calling its
+    /// property with the given name calls, using the method cache at the
+    /// second operand: the unbound method and the receiver for Lox methods
+    /// of instances, or otherwise the property's value and `nil`.
+    GetMethod(usize, usize),
+    /// Calls what `GetMethod` found with the given number of arguments.
+    Invoke(usize),
+    Closure(usize),
+    Class(usize),
+    /// Imports the module at the path in the given name slot.
+    Import(usize),
+    /// Installs a handler that catches errors raised before the matching
+    /// `PopHandler`, jumping to the target with the error value pushed.
+    Catch(usize),
+    /// Like `Catch`, but the handler runs a `finally` block and rethrows, so
+    /// the error is held by the `Vm` rather than pushed.
+    Finally(usize),
+    PopHandler,
+    Throw,
+    /// Rethrows the error held while a `finally` block ran.
+    Rethrow,
+    /// Drops the held error instead, when a `finally` block is left by a
+    /// jump or `return`.
+    DropPending,
+    Return,
+}
+
+#[derive(Debug)]
+pub struct ClassProto {
+    pub name:       Token,
+    pub superclass: Option<Token>,
+    pub methods:    Vec<Rc<FnStatic>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code:      Vec<Op>,
+    pub spans:     Vec<Span>,
+    pub constants: Vec<Value>,
+    pub names:     Vec<Symbol>,
+    pub functions: Vec<Rc<FnStatic>>,
+    pub classes:   Vec<ClassProto>,
+    pub caches:    Vec<MethodCache>,
+}
+
+impl Chunk {
+    fn write(&mut self, op: Op, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+        self.code.len() - 1
+    }
+
+    fn name<S>(&mut self, name: S) -> usize
+    where
+        S: Into<Symbol>,
+    {
+        let name = name.into();
+        if let Some(idx) = self.names.iter().position(|n| *n == name) {
+            return idx;
+        }
+        self.names.push(name);
+        self.names.len() - 1
+    }
+}
+
+/// Jumps out of the loop being compiled, patched once its end is known.
+#[derive(Debug)]
+struct Loop {
+    unwind:    usize,
+    breaks:    Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// Something a jump out of the middle of a block has to undo on its way.
+#[derive(Debug, Clone)]
+enum Unwind {
+    Scope,
+    Catch,
+    Finally(Vec<Stmt>),
+    /// A `finally` block run for an error it's about to rethrow.
+    Pending,
+}
+
+/// Lowers resolved statements into a `Chunk` for the `Vm`. Variables are
+/// addressed the same way the tree-walking `Interpreter` addresses them, by
+/// name for globals and by the slot computed by the `Resolver` for locals.
+#[derive(Debug)]
+pub struct Compiler {
+    chunk:       Chunk,
+    span:        Span,
+    loops:       Vec<Loop>,
+    unwind:      Vec<Unwind>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler {
+            chunk:       Chunk::default(),
+            span:        Span::default(),
+            loops:       vec![],
+            unwind:      vec![],
+        }
+    }
+
+    pub fn compile(stmts: &[Stmt]) -> Result<Chunk, LoxError> {
+        let mut compiler = Compiler::new();
+        for stmt in stmts {
+            compiler.visit(stmt)?;
+        }
+        Ok(compiler.finish())
+    }
+
+    fn finish(mut self) -> Chunk {
+        self.emit(Op::Nil);
+        self.emit(Op::Return);
+        self.chunk
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.write(op, self.span)
+    }
+
+    fn patch_jump(&mut self, at: usize) {
+        let target = self.chunk.code.len();
+        match &mut self.chunk.code[at] {
+            Op::Jump(dest) | Op::JumpIfFalse(dest) | Op::Catch(dest) | Op::Finally(dest) => {
+                *dest = target
+            },
+            op => panic!("attempted to patch non-jump instruction: {:?}", op),
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), LoxError> {
+        self.emit(Op::PushScope);
+        self.unwind.push(Unwind::Scope);
+        for stmt in stmts {
+            self.visit(stmt)?;
+        }
+        self.unwind.pop();
+        self.emit(Op::PopScope);
+        Ok(())
+    }
+
+    /// Undoes everything entered since the unwind stack was `level` deep,
+    /// innermost first, running `finally` blocks on the way out.
+    fn unwind_to(&mut self, level: usize) -> Result<(), LoxError> {
+        let entries = self.unwind.clone();
+        for idx in (level..entries.len()).rev() {
+            self.unwind.truncate(idx);
+            match &entries[idx] {
+                Unwind::Scope => {
+                    self.emit(Op::PopScope);
+                },
+                Unwind::Catch => {
+                    self.emit(Op::PopHandler);
+                },
+                Unwind::Finally(stmts) => {
+                    let span = self.span;
+                    self.emit(Op::PopHandler);
+                    self.block(stmts)?;
+                    self.span = span;
+                },
+                Unwind::Pending => {
+                    self.emit(Op::DropPending);
+                },
+            }
+        }
+        self.unwind = entries;
+        Ok(())
+    }
+
+    /// Unwinds out of the innermost loop and jumps out of it, returning the
+    /// jump to patch.
+    fn exit_loop(&mut self) -> Result<usize, LoxError> {
+        let level = self.loops.last().expect("jump outside of a loop").unwind;
+        self.unwind_to(level)?;
+        Ok(self.emit(Op::Jump(0)))
+    }
+
+    fn at(&mut self, token: &Token) {
+        self.span = token.span;
+    }
+
+    fn function(
+        &mut self,
+        name: &Token,
+        params: &[Token],
+        body: &[Stmt],
+        is_init: bool,
+    ) -> Result<Rc<FnStatic>, LoxError> {
+        let mut compiler = Compiler::new();
+        compiler.at(name);
+        for stmt in body {
+            compiler.visit(stmt)?;
+        }
+        Ok(FnStatic::compiled(name, params, compiler.finish(), is_init).into())
+    }
+}
+
+impl<'a> Visitor<&'a Expr> for Compiler {
+    type Output = Result<(), LoxError>;
+
+    fn visit(&mut self, expr: &'a Expr) -> Self::Output {
+        match expr {
+            Expr::Assign(name, value, slot) => {
+                self.visit(&**value)?;
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::SetVar(name, *slot));
+            },
+            Expr::Binary(left, op, right) => {
+                self.visit(&**left)?;
+                self.visit(&**right)?;
+                self.span = expr.span();
+                self.emit(Op::Binary(op.ty));
+            },
+            Expr::Call(callee, paren, args, _) => {
+                if let Expr::Get(object, name) = &**callee {
+                    self.visit(&**object)?;
+                    self.at(name);
+                    let name = self.chunk.name(name.symbol);
+                    self.chunk.caches.push(MethodCache::default());
+                    self.emit(Op::GetMethod(name, self.chunk.caches.len() - 1));
+                } else {
+                    self.visit(&**callee)?;
+                }
+                for arg in args {
+                    self.visit(arg)?;
+                }
+                self.at(paren);
+                if let Expr::Get(..) = &**callee {
+                    self.emit(Op::Invoke(args.len()));
+                } else {
+                    self.emit(Op::Call(args.len()));
+                }
+            },
+            Expr::Get(object, name) => {
+                self.visit(&**object)?;
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::GetProperty(name));
+            },
+            Expr::Grouping(expr, _) => self.visit(&**expr)?,
+            Expr::Index(object, bracket, index) => {
+                self.visit(&**object)?;
+                self.visit(&**index)?;
+                self.at(bracket);
+                self.emit(Op::GetIndex);
+            },
+            Expr::List(elements, span) => {
+                for element in elements {
+                    self.visit(element)?;
+                }
+                self.span = *span;
+                self.emit(Op::List(elements.len()));
+            },
+            Expr::Literal(value, span) => {
+                self.span = *span;
+                match value {
+                    Primitive::Nil => self.emit(Op::Nil),
+                    Primitive::Bool(true) => self.emit(Op::True),
+                    Primitive::Bool(false) => self.emit(Op::False),
+                    value => {
+                        self.chunk.constants.push(value.clone().into());
+                        let idx = self.chunk.constants.len() - 1;
+                        self.emit(Op::Constant(idx))
+                    },
+                };
+            },
+            Expr::Map(entries, span) => {
+                for (key, value) in entries {
+                    self.visit(key)?;
+                    self.visit(value)?;
+                }
+                self.span = *span;
+                self.emit(Op::Map(entries.len()));
+            },
+            Expr::Logical(left, op, right) => {
+                self.visit(&**left)?;
+                self.at(op);
+                let end = if op.ty == TokenType::Or {
+                    let otherwise = self.emit(Op::JumpIfFalse(0));
+                    let end = self.emit(Op::Jump(0));
+                    self.patch_jump(otherwise);
+                    end
+                } else {
+                    self.emit(Op::JumpIfFalse(0))
+                };
+                self.emit(Op::Pop);
+                self.visit(&**right)?;
+                self.patch_jump(end);
+            },
+            Expr::Set(object, name, value) => {
+                self.visit(&**object)?;
+                self.visit(&**value)?;
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::SetProperty(name));
+            },
+            Expr::SetIndex(object, bracket, index, value) => {
+                self.visit(&**object)?;
+                self.visit(&**index)?;
+                self.visit(&**value)?;
+                self.at(bracket);
+                self.emit(Op::SetIndex);
+            },
+            Expr::Super(kw, method, slot) => {
+                self.at(kw);
+                let method = self.chunk.name(method.symbol);
+                self.emit(Op::GetSuper(method, *slot));
+            },
+            Expr::This(kw, slot) => {
+                self.at(kw);
+                let name = self.chunk.name(Symbol::THIS);
+                self.emit(Op::GetVar(name, *slot));
+            },
+            Expr::Unary(op, right) => {
+                self.visit(&**right)?;
+                self.at(op);
+                self.emit(Op::Unary(op.ty));
+            },
+            Expr::Variable(name, slot) => {
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::GetVar(name, *slot));
+            },
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Visitor<&'a Stmt> for Compiler {
+    type Output = Result<(), LoxError>;
+
+    fn visit(&mut self, stmt: &'a Stmt) -> Self::Output {
+        match stmt {
+            Stmt::Block(stmts, _) => self.block(stmts)?,
+            Stmt::Break(kw) => {
+                self.at(kw);
+                let jump = self.exit_loop()?;
+                self.loops.last_mut().unwrap().breaks.push(jump);
+            },
+            Stmt::Continue(kw) => {
+                self.at(kw);
+                let jump = self.exit_loop()?;
+                self.loops.last_mut().unwrap().continues.push(jump);
+            },
+            Stmt::Class(name, superclass, body) => {
+                let superclass = if let Some(superclass) = superclass {
+                    self.visit(superclass)?;
+                    if let Expr::Variable(name, _) = superclass {
+                        Some(name.clone())
+                    } else {
+                        unreachable!()
+                    }
+                } else {
+                    None
+                };
+                let mut methods = vec![];
+                for method in body {
+                    if let Stmt::Function(name, params, body) = method {
+                        methods.push(self.function(name, params, body, &*name.lexeme == "init")?);
+                    } else {
+                        unreachable!()
+                    }
+                }
+                self.at(name);
+                self.chunk.classes.push(ClassProto {
+                    name: name.clone(),
+                    superclass,
+                    methods,
+                });
+                let idx = self.chunk.classes.len() - 1;
+                self.emit(Op::Class(idx));
+            },
+            Stmt::Expr(expr) => {
+                self.visit(expr)?;
+                self.emit(Op::Pop);
+            },
+            Stmt::Function(name, params, body) => {
+                let function = self.function(name, params, body, false)?;
+                self.at(name);
+                self.chunk.functions.push(function);
+                let idx = self.chunk.functions.len() - 1;
+                self.emit(Op::Closure(idx));
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::Define(name));
+            },
+            Stmt::If(cond, then, otherwise, _) => {
+                self.visit(cond)?;
+                let then_jump = self.emit(Op::JumpIfFalse(0));
+                self.emit(Op::Pop);
+                self.visit(&**then)?;
+                let else_jump = self.emit(Op::Jump(0));
+                self.patch_jump(then_jump);
+                self.emit(Op::Pop);
+                if let Some(otherwise) = otherwise {
+                    self.visit(&**otherwise)?;
+                }
+                self.patch_jump(else_jump);
+            },
+            Stmt::Print(expr, _) => {
+                self.visit(expr)?;
+                self.emit(Op::Print);
+            },
+            Stmt::Return(kw, expr) => {
+                if let Some(expr) = expr {
+                    self.visit(expr)?;
+                } else {
+                    self.emit(Op::Nil);
+                }
+                self.at(kw);
+                self.unwind_to(0)?;
+                self.emit(Op::Return);
+            },
+            Stmt::Import(_, path, name) => {
+                self.at(path);
+                let path = self.chunk.name(path.literal.to_string());
+                self.emit(Op::Import(path));
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::Define(name));
+            },
+            Stmt::Throw(kw, value) => {
+                self.visit(value)?;
+                self.at(kw);
+                self.emit(Op::Throw);
+            },
+            Stmt::Try(kw, body, catch, finally) => {
+                self.at(kw);
+                let finally_handler = finally.as_ref().map(|finally| {
+                    self.unwind.push(Unwind::Finally(finally.clone()));
+                    self.emit(Op::Finally(0))
+                });
+
+                if let Some((name, handler)) = catch {
+                    self.unwind.push(Unwind::Catch);
+                    let catch_handler = self.emit(Op::Catch(0));
+                    self.block(body)?;
+                    self.unwind.pop();
+                    self.emit(Op::PopHandler);
+                    let end = self.emit(Op::Jump(0));
+
+                    self.patch_jump(catch_handler);
+                    self.emit(Op::PushScope);
+                    self.unwind.push(Unwind::Scope);
+                    self.at(name);
+                    let name = self.chunk.name(name.symbol);
+                    self.emit(Op::Define(name));
+                    for stmt in handler {
+                        self.visit(stmt)?;
+                    }
+                    self.unwind.pop();
+                    self.emit(Op::PopScope);
+                    self.patch_jump(end);
+                } else {
+                    self.block(body)?;
+                }
+
+                if let (Some(finally_handler), Some(finally)) = (finally_handler, finally) {
+                    self.unwind.pop();
+                    self.at(kw);
+                    self.emit(Op::PopHandler);
+                    self.block(finally)?;
+                    let end = self.emit(Op::Jump(0));
+                    self.patch_jump(finally_handler);
+                    self.unwind.push(Unwind::Pending);
+                    self.block(finally)?;
+                    self.unwind.pop();
+                    self.emit(Op::Rethrow);
+                    self.patch_jump(end);
+                }
+            },
+            Stmt::Var(name, init) => {
+                self.visit(init)?;
+                self.at(name);
+                let name = self.chunk.name(name.symbol);
+                self.emit(Op::Define(name));
+            },
+            Stmt::While(cond, body, inc, span) => {
+                let start = self.chunk.code.len();
+                self.visit(cond)?;
+                let exit = self.emit(Op::JumpIfFalse(0));
+                self.emit(Op::Pop);
+                self.loops.push(Loop {
+                    unwind:    self.unwind.len(),
+                    breaks:    vec![],
+                    continues: vec![],
+                });
+                self.visit(&**body)?;
+                let body = self.loops.pop().unwrap();
+                for jump in body.continues {
+                    self.patch_jump(jump);
+                }
+                if let Some(inc) = inc {
+                    self.visit(inc)?;
+                    self.emit(Op::Pop);
+                }
+                // The jump back has no token of its own, but limits can halt
+                // the loop on it.
+                self.span = *span;
+                self.emit(Op::Jump(start));
+                self.patch_jump(exit);
+                self.emit(Op::Pop);
+                for jump in body.breaks {
+                    self.patch_jump(jump);
+                }
+            },
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    fmt::{
+        self,
+        Write,
+    },
+    io::{
+        self,
+        IsTerminal,
+    },
+};
+
+use crate::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn color(self) -> &'static str {
+        match self {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span:    Span,
+    pub message: String,
+}
+
+/// A fully described problem with the source: what went wrong, where, and
+/// how to fix it. Rendered in the style of rustc's error output.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code:     Option<&'static str>,
+    pub message:  String,
+    pub location: Option<Location>,
+    pub label:    Option<String>,
+    pub labels:   Vec<Label>,
+    pub help:     Vec<String>,
+    pub trace:    Option<StackTrace>,
+}
+
+/// Types that can be rendered as a `Diagnostic` by a `Reporter`.
+pub trait ToDiagnostic {
+    fn to_diagnostic(&self) -> Diagnostic;
+
+    /// Errors from another file that caused this one, rendered with that
+    /// file's source.
+    fn nested(&self) -> Option<String> {
+        None
+    }
+}
+
+/// The text that diagnostics point into.
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub file: Option<String>,
+    pub text: String,
+}
+
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.into()
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new<S>(severity: Severity, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.into(),
+            location: None,
+            label: None,
+            labels: vec![],
+            help: vec![],
+            trace: None,
+        }
+    }
+
+    pub fn error<S>(message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning<S>(message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_location<L>(mut self, location: L) -> Self
+    where
+        L: Into<Location>,
+    {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Sets the message printed under the primary span.
+    pub fn with_label<S>(mut self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.label = Some(message.into());
+        self
+    }
+
+    /// Adds a secondary span, underlined with `-` rather than `^`.
+    pub fn with_secondary<S>(mut self, span: Span, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_trace(mut self, trace: StackTrace) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    pub fn with_help<S>(mut self, help: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Whether stdout is a terminal and so should get colored diagnostics.
+    pub fn use_color() -> bool {
+        io::stdout().is_terminal()
+    }
+
+    /// Renders the diagnostic, quoting `source` if it's the file the
+    /// diagnostic points into.
+    pub fn render(&self, source: Option<&Source>, color: bool) -> String {
+        let style = Style {
+            color,
+        };
+        let mut out = String::new();
+
+        let severity = match self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        };
+        let _ = write!(
+            out,
+            "{}{}",
+            style.paint(self.severity.color(), &severity),
+            style.paint(BOLD, &format!(": {}", self.message)),
+        );
+
+        let location = match &self.location {
+            Some(location) => location,
+            None => {
+                self.render_help(&mut out, &style, 0);
+                return out;
+            },
+        };
+
+        let source = source.filter(|source| source.file == location.file);
+        let snippets = source
+            .map(|source| self.snippets(location.span, &source.text))
+            .unwrap_or_default();
+        let gutter = snippets
+            .iter()
+            .map(|snippet| snippet.line.to_string().len())
+            .max()
+            .unwrap_or(0)
+            .max(location.span.line.to_string().len());
+
+        let _ = write!(
+            out,
+            "\n{}{} {}:{}:{}",
+            " ".repeat(gutter),
+            style.paint(BLUE, "-->"),
+            location.file.as_deref().unwrap_or("<input>"),
+            location.span.line,
+            location.span.column,
+        );
+
+        if !snippets.is_empty() {
+            let bar = style.paint(BLUE, "|");
+            let _ = write!(out, "\n{} {}", " ".repeat(gutter), bar);
+            for snippet in snippets {
+                let number = format!("{:>width$}", snippet.line, width = gutter);
+                let _ = write!(out, "\n{} {} {}", style.paint(BLUE, &number), bar, snippet.text);
+                for mark in snippet.marks {
+                    let (underline, color) = if mark.primary {
+                        ("^", self.severity.color())
+                    } else {
+                        ("-", BLUE)
+                    };
+                    let underline = format!("{} {}", underline.repeat(mark.width), mark.message);
+                    let _ = write!(
+                        out,
+                        "\n{} {} {}{}",
+                        " ".repeat(gutter),
+                        bar,
+                        " ".repeat(mark.column),
+                        style.paint(color, underline.trim_end()),
+                    );
+                }
+            }
+        }
+
+        self.render_help(&mut out, &style, gutter);
+        if let Some(trace) = &self.trace {
+            let _ = write!(
+                out,
+                "\n{} {} {}",
+                " ".repeat(gutter),
+                style.paint(BLUE, "="),
+                style.paint(BOLD, "backtrace:"),
+            );
+            for line in trace.rendered() {
+                let _ = write!(out, "\n{}     {}", " ".repeat(gutter), line);
+            }
+        }
+        out
+    }
+
+    fn render_help(&self, out: &mut String, style: &Style, gutter: usize) {
+        for help in &self.help {
+            let _ = write!(
+                out,
+                "\n{} {} {}",
+                " ".repeat(gutter),
+                style.paint(BLUE, "="),
+                style.paint(BOLD, &format!("help: {}", help)),
+            );
+        }
+    }
+
+    /// Groups the primary and secondary labels by source line.
+    fn snippets(&self, primary: Span, text: &str) -> Vec<Snippet> {
+        let mut labels = vec![(primary, true, self.label.clone().unwrap_or_default())];
+        labels.extend(self.labels.iter().map(|label| (label.span, false, label.message.clone())));
+
+        let mut snippets: Vec<Snippet> = vec![];
+        for (span, primary, message) in labels {
+            // Spans from another run of the REPL may not point into `text`.
+            let end = span.end.min(text.len());
+            if !text.is_char_boundary(span.start)
+                || !text.is_char_boundary(end)
+                || text[..span.start].matches('\n').count() + 1 != span.line
+            {
+                continue;
+            }
+            let line_start = text[..span.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end =
+                text[line_start..].find('\n').map(|i| line_start + i).unwrap_or(text.len());
+            let end = span.end.min(line_end).max(span.start);
+            let mark = Mark {
+                column: text[line_start..span.start].chars().count(),
+                width: text[span.start..end].chars().count().max(1),
+                primary,
+                message,
+            };
+
+            match snippets.iter_mut().find(|snippet| snippet.line == span.line) {
+                Some(snippet) => snippet.marks.push(mark),
+                None => snippets.push(Snippet {
+                    line:  span.line,
+                    text:  text[line_start..line_end].trim_end_matches('\r').into(),
+                    marks: vec![mark],
+                }),
+            }
+        }
+
+        snippets.sort_by_key(|snippet| snippet.line);
+        for snippet in &mut snippets {
+            snippet.marks.sort_by_key(|mark| mark.column);
+        }
+        snippets
+    }
+}
+
+/// A quoted source line and the marks underlining it.
+struct Snippet {
+    line:  usize,
+    text:  String,
+    marks: Vec<Mark>,
+}
+
+struct Mark {
+    column:  usize,
+    width:   usize,
+    primary: bool,
+    message: String,
+}
+
+impl ToDiagnostic for LoxError {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            LoxError::Scan(_, msg) => Diagnostic::error(msg.clone()),
+            LoxError::Parse(_, at, msg) => Diagnostic::error(msg.clone()).with_label(at.trim()),
+            LoxError::Resolve(_, at, kind) => {
+                Diagnostic::error(kind.to_string()).with_label(at.trim())
+            },
+            LoxError::Cast(msg, _) => Diagnostic::error(msg.clone()),
+            LoxError::Runtime(_, msg, trace, _) if !trace.is_empty() => {
+                Diagnostic::error(msg.clone()).with_trace(trace.clone())
+            },
+            LoxError::Runtime(_, msg, ..) => Diagnostic::error(msg.clone()),
+            LoxError::Throw(_, description, trace) => {
+                let diagnostic =
+                    Diagnostic::error(format!("uncaught exception: {}", description));
+                if trace.is_empty() {
+                    diagnostic
+                } else {
+                    diagnostic.with_trace(trace.clone())
+                }
+            },
+            LoxError::Exit(code) => Diagnostic::error(format!("script exited with code {}", code)),
+            LoxError::Halt(_, msg, trace) if !trace.is_empty() => {
+                Diagnostic::error(msg.clone()).with_trace(trace.clone())
+            },
+            LoxError::Halt(_, msg, _) => Diagnostic::error(msg.clone()),
+            LoxError::Import(_, path, _) => {
+                Diagnostic::error(format!("could not import {}", path))
+                    .with_help("the errors in the imported file are shown below")
+            },
+        }
+        .with_code(self.code());
+
+        match self.location() {
+            Some(location) => diagnostic.with_location(location.clone()),
+            None => diagnostic,
+        }
+    }
+
+    fn nested(&self) -> Option<String> {
+        match self {
+            LoxError::Import(_, _, errors) => Some(errors.to_string()),
+            _ => None,
+        }
+    }
+}
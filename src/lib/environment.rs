@@ -1,50 +1,115 @@
 use std::{
-    borrow::Borrow,
     cell::RefCell,
-    collections::HashMap,
-    hash::Hash,
-    mem::replace,
+    mem::{
+        self,
+        replace,
+    },
     rc::Rc,
 };
 
 use crate::*;
 
+/// Where the `Resolver` found a local variable: how many scopes out from the
+/// one it's used in, and its index among that scope's locals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+impl Slot {
+    /// The slot of `this` in a method of the class whose `super` is at this
+    /// slot: `this` is the first variable of the method's own scope, the
+    /// next one in.
+    pub fn this(self) -> Slot {
+        Slot {
+            depth: self.depth - 1,
+            index: 0,
+        }
+    }
+}
+
+/// A scope's variables. Globals are kept by name, since they can be defined
+/// and looked up after their code has been resolved, while locals are kept
+/// in the order they're declared, which is the order the `Resolver` gives
+/// them slots in.
 #[derive(Default, Debug)]
 pub struct Scope {
-    values:    HashMap<LoxStr, Value>,
+    values:    SymbolMap<Value>,
+    slots:     Vec<Value>,
     enclosing: Option<ScopeHandle>,
 }
 
 type ScopeHandle = Rc<RefCell<Scope>>;
 
 impl Scope {
-    pub fn get<K>(&self, name: &K) -> Option<Value>
-    where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
-    {
-        self.values.get(name).map(Clone::clone).or_else(|| {
+    /// Allocates a scope, registering it with the cycle collector.
+    fn handle(enclosing: Option<ScopeHandle>) -> ScopeHandle {
+        let scope = Rc::new(RefCell::new(Scope {
+            values: Default::default(),
+            slots:  Vec::new(),
+            enclosing,
+        }));
+        track(&scope);
+        scope
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<Value> {
+        self.values.get(&name).cloned().or_else(|| {
             self.enclosing.as_ref().and_then(|enclosing| RefCell::borrow(enclosing).get(name))
         })
     }
 
-    pub fn assign<K>(&mut self, name: &K, value: Value) -> Option<Value>
-    where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
-    {
-        if self.values.contains_key(name) {
-            return Some(replace(self.values.get_mut(name).unwrap(), value));
+    pub fn assign(&mut self, name: Symbol, value: Value) -> Option<Value> {
+        if let Some(slot) = self.values.get_mut(&name) {
+            return Some(replace(slot, value));
         }
 
         self.enclosing.as_ref().and_then(|enclosing| enclosing.borrow_mut().assign(name, value))
     }
 
-    pub fn define<K>(&mut self, name: K, value: Value)
-    where
-        K: Into<LoxStr>,
-    {
-        self.values.insert(name.into(), value);
+    pub fn define(&mut self, name: Symbol, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    fn get_slot(&self, depth: usize, index: usize) -> Option<Value> {
+        if depth == 0 {
+            self.slots.get(index).cloned()
+        } else {
+            RefCell::borrow(self.enclosing.as_ref()?).get_slot(depth - 1, index)
+        }
+    }
+
+    fn assign_slot(&mut self, depth: usize, index: usize, value: Value) -> Option<Value> {
+        if depth == 0 {
+            self.slots.get_mut(index).map(|slot| replace(slot, value))
+        } else {
+            self.enclosing.as_ref()?.borrow_mut().assign_slot(depth - 1, index, value)
+        }
+    }
+}
+
+impl Trace for Scope {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self.values.values().chain(&self.slots) {
+            tracer.value(value);
+        }
+        if let Some(enclosing) = &self.enclosing {
+            tracer.object(enclosing);
+        }
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<Scope>()
+            + self.values.capacity() * mem::size_of::<(Symbol, Value)>()
+            + self.slots.capacity() * mem::size_of::<Value>()
+            + strings_size(self.values.values().chain(&self.slots))
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.slots.clear();
+        self.enclosing = None;
     }
 }
 
@@ -56,7 +121,7 @@ pub struct Environment {
 
 impl Environment {
     pub fn new() -> Self {
-        let global = Rc::new(RefCell::new(Scope::default()));
+        let global = Scope::handle(None);
         let scope = global.clone();
         Environment {
             global,
@@ -64,59 +129,79 @@ impl Environment {
         }
     }
 
+    /// A fresh global scope for a module, falling back to this
+    /// environment's globals for names it doesn't define.
+    pub fn new_module(&self) -> Self {
+        let global = Scope::handle(Some(self.global.clone()));
+        Environment {
+            global: global.clone(),
+            scope:  global,
+        }
+    }
+
     pub fn with_enclosing(parent: &Environment) -> Self {
         Environment {
             global: parent.global.clone(),
-            scope:  Rc::new(RefCell::new(Scope {
-                values:    Default::default(),
-                enclosing: Some(parent.scope.clone()),
-            })),
+            scope:  Scope::handle(Some(parent.scope.clone())),
         }
     }
 
+    /// Defines a variable in the current scope: by name at the top level,
+    /// and otherwise in the scope's next slot.
     pub fn define<S>(&mut self, name: S, value: Value)
     where
-        S: Into<LoxStr>,
+        S: Into<Symbol>,
     {
-        self.scope.borrow_mut().define(name, value)
+        if Rc::ptr_eq(&self.scope, &self.global) {
+            self.global.borrow_mut().define(name.into(), value)
+        } else {
+            self.scope.borrow_mut().slots.push(value)
+        }
     }
 
-    pub fn assign<K>(&mut self, name: &K, value: Value) -> Option<Value>
+    pub fn get<S>(&self, name: S) -> Option<Value>
     where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
+        S: Into<Symbol>,
     {
-        self.scope.borrow_mut().assign(name, value)
+        RefCell::borrow(&self.scope).get(name.into())
     }
 
-    pub fn get<K>(&self, name: &K) -> Option<Value>
+    /// Looks `name` up in the innermost scope only.
+    pub fn get_local<S>(&self, name: S) -> Option<Value>
     where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
+        S: Into<Symbol>,
     {
-        RefCell::borrow(&self.scope).get(name)
+        RefCell::borrow(&self.scope).values.get(&name.into()).cloned()
     }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.object(&self.global);
+        tracer.object(&self.scope);
+    }
+
+    pub fn same_scope(&self, other: &Environment) -> bool {
+        Rc::ptr_eq(&self.scope, &other.scope)
+    }
+
     pub fn define_global<S>(&self, name: S, value: Value)
     where
-        S: Into<LoxStr>,
+        S: Into<Symbol>,
     {
-        self.global.borrow_mut().define(name, value)
+        self.global.borrow_mut().define(name.into(), value)
     }
 
-    pub fn assign_global<K>(&mut self, name: &K, value: Value) -> Option<Value>
+    pub fn assign_global<S>(&mut self, name: S, value: Value) -> Option<Value>
     where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
+        S: Into<Symbol>,
     {
-        self.global.borrow_mut().assign(name, value)
+        self.global.borrow_mut().assign(name.into(), value)
     }
 
-    pub fn get_global<K>(&self, name: &K) -> Option<Value>
+    pub fn get_global<S>(&self, name: S) -> Option<Value>
     where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
+        S: Into<Symbol>,
     {
-        RefCell::borrow(&self.global).get(name)
+        RefCell::borrow(&self.global).get(name.into())
     }
 
     pub fn get_enclosing(&self) -> Option<Environment> {
@@ -126,24 +211,23 @@ impl Environment {
         })
     }
 
-    pub fn ancestor(&self, depth: usize) -> Option<Environment> {
-        let mut ret = Some(self.clone());
-        for _ in 0..depth {
-            ret = ret.and_then(|ret| ret.get_enclosing());
+    pub fn get_at<S>(&self, name: S, slot: Option<Slot>) -> Option<Value>
+    where
+        S: Into<Symbol>,
+    {
+        match slot {
+            Some(slot) => RefCell::borrow(&self.scope).get_slot(slot.depth, slot.index),
+            None => self.get_global(name),
         }
-        ret
     }
 
-    pub fn get_at<K, I>(&self, name: &K, depth: I) -> Option<Value>
+    pub fn assign_at<S>(&mut self, name: S, value: Value, slot: Option<Slot>) -> Option<Value>
     where
-        K: Hash + Eq + ?Sized,
-        LoxStr: Borrow<K>,
-        I: Into<Option<usize>>,
+        S: Into<Symbol>,
     {
-        if let Some(depth) = depth.into() {
-            self.ancestor(depth).and_then(|e| e.get(name))
-        } else {
-            self.get_global(name)
+        match slot {
+            Some(slot) => self.scope.borrow_mut().assign_slot(slot.depth, slot.index, value),
+            None => self.assign_global(name, value),
         }
     }
 }
@@ -12,11 +12,12 @@ use crate::*;
 #[derive(Debug)]
 pub struct Reporter<E> {
     errors: Vec<E>,
+    source: Option<Source>,
 }
 
 impl<E> Default for Reporter<E>
 where
-    E: Fail,
+    E: Fail + ToDiagnostic,
 {
     fn default() -> Self {
         Reporter::new()
@@ -24,31 +25,86 @@ where
 }
 
 #[derive(Fail, Debug)]
-pub struct Errors<E: Fail>(Vec<E>);
+pub struct Errors<E: Fail + ToDiagnostic> {
+    errors: Vec<E>,
+    source: Option<Source>,
+}
 
 impl<E> Display for Errors<E>
 where
-    E: Fail,
+    E: Fail + ToDiagnostic,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for (idx, err) in self.0.iter().enumerate() {
-            if idx == self.0.len() - 1 {
-                write!(f, "{}", err)?;
+        let color = Diagnostic::use_color();
+        for (idx, err) in self.errors.iter().enumerate() {
+            let mut rendered = err.to_diagnostic().render(self.source.as_ref(), color);
+            if let Some(nested) = err.nested() {
+                rendered = format!("{}\n\n{}", rendered, nested);
+            }
+            if idx == self.errors.len() - 1 {
+                write!(f, "{}", rendered)?;
             } else {
-                writeln!(f, "{}", err)?;
+                writeln!(f, "{}\n", rendered)?;
             }
         }
         Ok(())
     }
 }
 
+impl<E> Errors<E>
+where
+    E: Fail + ToDiagnostic,
+{
+    /// The errors, in the order they were reported.
+    pub fn errors(&self) -> &[E] {
+        &self.errors
+    }
+}
+
+impl<E> From<E> for Errors<E>
+where
+    E: Fail + ToDiagnostic,
+{
+    fn from(error: E) -> Self {
+        Errors {
+            errors: vec![error],
+            source: None,
+        }
+    }
+}
+
+impl Errors<LoxError> {
+    /// The code a script asked to exit with, if it stopped by calling `exit()`.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.errors.as_slice() {
+            [LoxError::Exit(code)] => Some(*code),
+            _ => None,
+        }
+    }
+}
+
 impl<E> Reporter<E>
 where
-    E: Fail,
+    E: Fail + ToDiagnostic,
 {
     pub fn new() -> Reporter<E> {
         Reporter {
             errors: vec![],
+            source: None,
+        }
+    }
+
+    /// A reporter whose diagnostics quote lines from `text`.
+    pub fn with_source<S>(file: Option<&str>, text: S) -> Reporter<E>
+    where
+        S: Into<String>,
+    {
+        Reporter {
+            errors: vec![],
+            source: Some(Source {
+                file: file.map(Into::into),
+                text: text.into(),
+            }),
         }
     }
 
@@ -56,7 +112,10 @@ where
         if self.errors.len() == 0 {
             Ok(())
         } else {
-            Err(Errors(self.errors))
+            Err(Errors {
+                errors: self.errors,
+                source: self.source,
+            })
         }
     }
 
@@ -64,6 +123,13 @@ where
         self.errors.push(error);
     }
 
+    /// Puts the errors in the order they appear in the source.
+    pub fn sort(&mut self) {
+        self.errors.sort_by_cached_key(|err| {
+            err.to_diagnostic().location.map(|location| location.span.start)
+        });
+    }
+
     pub fn filter<'s, 'i, 'r, I, T>(&'s mut self, i: I) -> impl Iterator<Item = T> + 'r
     where
         I: IntoIterator<Item = Result<T, E>> + 'i,
@@ -79,57 +145,288 @@ where
         })
     }
 
-    pub fn join<T>(&mut self, mut other: Reporter<T>)
+    pub fn join<T>(&mut self, other: Reporter<T>)
     where
         E: From<T>,
     {
-        self.errors.extend(other.errors.drain(..).map(From::from));
+        self.join_with(other, From::from);
+    }
+
+    /// Takes on the errors `other` reported, converting them with `f`.
+    pub fn join_with<T, F>(&mut self, mut other: Reporter<T>, f: F)
+    where
+        F: FnMut(T) -> E,
+    {
+        self.errors.extend(other.errors.drain(..).map(f));
     }
 }
 
 #[derive(Fail, Debug, Display)]
 pub enum LoxError {
-    #[display(fmt = "[line {}] Error: {}", _0, _1)]
-    Scan(usize, String),
-    #[display(fmt = "[line {}] Error{}: {}", _0, _1, _2)]
-    Parse(usize, String, String),
+    #[display(fmt = "{} Error: {}", _0, _1)]
+    Scan(Location, String),
+    #[display(fmt = "{} Error{}: {}", _0, _1, _2)]
+    Parse(Location, String, String),
+    /// Code that parses but breaks the rules checked by the `Resolver`.
+    #[display(fmt = "{} Error{}: {}", _0, _1, _2)]
+    Resolve(Location, String, ResolveError),
     #[display(fmt = "{}", _0)]
-    Cast(String),
-    #[display(fmt = "[line {}] Error: {}", _0, _1)]
-    Runtime(usize, String),
+    Cast(String, ErrorKind),
+    #[display(fmt = "{} Error: {}{}", _0, _1, _2)]
+    Runtime(Location, String, StackTrace, ErrorKind),
+    /// A value thrown with `throw`. The value itself is held by the
+    /// `Interpreter` until it's caught; this carries its description.
+    #[display(fmt = "{} Error: uncaught exception: {}{}", _0, _1, _2)]
+    Throw(Location, String, StackTrace),
+    /// A call to `exit()`, unwinding the whole script with the exit code.
+    #[display(fmt = "exit({})", _0)]
+    Exit(i32),
+    /// A script stopped for exceeding its `Limits` or being interrupted.
+    /// Like exits, these unwind the whole script.
+    #[display(fmt = "{} Error: {}{}", _0, _1, _2)]
+    Halt(Location, String, StackTrace),
+    /// An imported file that failed to scan, parse or resolve, with the
+    /// path it was imported by and the errors found in it.
+    #[display(fmt = "{} Error: could not import {}", _0, _1)]
+    Import(Location, String, Box<Errors<LoxError>>),
+}
+
+/// What went wrong in a runtime error, which decides the class of the value
+/// a `catch` clause receives for it. Displays as the class's name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum ErrorKind {
+    #[display(fmt = "RuntimeError")]
+    Runtime,
+    /// A value of the wrong type, such as adding `nil` or calling a number.
+    #[display(fmt = "TypeError")]
+    Type,
+    /// An undefined variable, property, method or module member.
+    #[display(fmt = "NameError")]
+    Name,
+    /// A call with the wrong number of arguments.
+    #[display(fmt = "ArityError")]
+    Arity,
+    /// An index out of range, a missing map key or an empty list popped.
+    #[display(fmt = "IndexError")]
+    Index,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 5] = [
+        ErrorKind::Runtime,
+        ErrorKind::Type,
+        ErrorKind::Name,
+        ErrorKind::Arity,
+        ErrorKind::Index,
+    ];
+}
+
+/// The mistakes the `Resolver` finds in code that parses, each with its own
+/// error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ResolveError {
+    #[display(fmt = "cannot return from top-level code")]
+    TopLevelReturn,
+    #[display(fmt = "cannot return a value from an initializer")]
+    InitializerReturn,
+    #[display(fmt = "cannot use 'this' outside of a class")]
+    ThisOutsideClass,
+    #[display(fmt = "cannot use 'super' outside of a class")]
+    SuperOutsideClass,
+    #[display(fmt = "cannot use 'super' in a class with no superclass")]
+    SuperWithoutSuperclass,
+    #[display(fmt = "a class cannot inherit from itself")]
+    InheritFromSelf,
+    #[display(fmt = "already a variable with this name in this scope")]
+    DuplicateDeclaration,
+    #[display(fmt = "cannot read local variable in its own initializer")]
+    OwnInitializer,
+    #[display(fmt = "cannot use 'break' outside of a loop")]
+    BreakOutsideLoop,
+    #[display(fmt = "cannot use 'continue' outside of a loop")]
+    ContinueOutsideLoop,
+}
+
+impl ResolveError {
+    pub fn code(self) -> &'static str {
+        match self {
+            ResolveError::TopLevelReturn => "E0009",
+            ResolveError::InitializerReturn => "E0010",
+            ResolveError::ThisOutsideClass => "E0011",
+            ResolveError::SuperOutsideClass => "E0012",
+            ResolveError::SuperWithoutSuperclass => "E0013",
+            ResolveError::InheritFromSelf => "E0014",
+            ResolveError::DuplicateDeclaration => "E0015",
+            ResolveError::OwnInitializer => "E0016",
+            ResolveError::BreakOutsideLoop => "E0017",
+            ResolveError::ContinueOutsideLoop => "E0018",
+        }
+    }
 }
 
 impl LoxError {
-    pub fn scan<S>(line: usize, msg: S) -> LoxError
+    pub fn scan<S>(span: Span, msg: S) -> LoxError
     where
         S: Into<String>,
     {
-        LoxError::Scan(line, msg.into())
+        LoxError::Scan(span.into(), msg.into())
     }
 
     pub fn parse<S>(token: &Token, msg: S) -> LoxError
     where
         S: Into<String>,
     {
-        let loc = if token.ty == TokenType::Eof {
-            " at end".into()
-        } else {
-            format!(" at \"{}\"", token.lexeme)
-        };
-        LoxError::Parse(token.line, loc, msg.into())
+        LoxError::Parse(token.span.into(), at(token), msg.into())
+    }
+
+    pub fn resolve(token: &Token, kind: ResolveError) -> LoxError {
+        LoxError::Resolve(token.span.into(), at(token), kind)
     }
 
     pub fn runtime<S>(token: &Token, msg: S) -> LoxError
     where
         S: Into<String>,
     {
-        LoxError::Runtime(token.line, msg.into())
+        LoxError::Runtime(token.span.into(), msg.into(), StackTrace::default(), ErrorKind::Runtime)
+    }
+
+    pub fn runtime_at<S>(span: Span, msg: S) -> LoxError
+    where
+        S: Into<String>,
+    {
+        LoxError::Runtime(span.into(), msg.into(), StackTrace::default(), ErrorKind::Runtime)
+    }
+
+    pub fn throw<S>(span: Span, description: S) -> LoxError
+    where
+        S: Into<String>,
+    {
+        LoxError::Throw(span.into(), description.into(), StackTrace::default())
+    }
+
+    pub fn halt<S>(span: Span, msg: S) -> LoxError
+    where
+        S: Into<String>,
+    {
+        LoxError::Halt(span.into(), msg.into(), StackTrace::default())
     }
 
+    /// A type error raised away from the source, by a native or a value
+    /// operation, and placed later with `or_at`.
     pub fn typecast<S>(msg: S) -> LoxError
     where
         S: Into<String>,
     {
-        LoxError::Cast(msg.into())
+        LoxError::Cast(msg.into(), ErrorKind::Type)
+    }
+
+    /// Changes the kind of a runtime or type error.
+    pub fn with_kind(mut self, kind: ErrorKind) -> LoxError {
+        if let LoxError::Cast(_, k) | LoxError::Runtime(_, _, _, k) = &mut self {
+            *k = kind;
+        }
+        self
+    }
+
+    /// The kind of a runtime or type error.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            LoxError::Cast(_, kind) | LoxError::Runtime(_, _, _, kind) => Some(*kind),
+            _ => None,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            LoxError::Scan(loc, _)
+            | LoxError::Parse(loc, _, _)
+            | LoxError::Resolve(loc, _, _)
+            | LoxError::Runtime(loc, ..)
+            | LoxError::Throw(loc, _, _)
+            | LoxError::Halt(loc, _, _)
+            | LoxError::Import(loc, _, _) => {
+                Some(loc)
+            },
+            LoxError::Cast(..) | LoxError::Exit(_) => None,
+        }
+    }
+
+    /// A stable identifier for the kind of error, shown in diagnostics.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LoxError::Scan(..) => "E0001",
+            LoxError::Parse(..) => "E0002",
+            LoxError::Resolve(_, _, kind) => kind.code(),
+            LoxError::Cast(..) => "E0003",
+            LoxError::Runtime(..) => "E0004",
+            LoxError::Throw(..) => "E0005",
+            LoxError::Exit(..) => "E0006",
+            LoxError::Halt(..) => "E0007",
+            LoxError::Import(..) => "E0008",
+        }
+    }
+
+    /// Whether `catch` blocks can handle this error. Exits and halts unwind
+    /// straight out of the script, skipping `catch` and `finally` alike.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self, LoxError::Exit(_) | LoxError::Halt(..))
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.location().map(|loc| loc.span)
+    }
+
+    /// Places a locationless type error at `span`, turning it into a runtime
+    /// error. Used for errors raised by natives, which don't know where they
+    /// were called from.
+    pub fn or_at(self, span: Span) -> LoxError {
+        match self {
+            LoxError::Cast(msg, kind) => LoxError::runtime_at(span, msg).with_kind(kind),
+            err => err,
+        }
+    }
+
+    /// Records the call stack on a runtime error that doesn't have one yet.
+    pub fn with_trace(mut self, frames: &[CallFrame]) -> LoxError {
+        if let LoxError::Runtime(loc, _, trace, _)
+        | LoxError::Throw(loc, _, trace)
+        | LoxError::Halt(loc, _, trace) = &mut self
+        {
+            if trace.is_empty() && !frames.is_empty() {
+                *trace = StackTrace::capture(frames, loc.span);
+            }
+        }
+        self
+    }
+
+    /// Attributes this error to `file`, unless it already names one.
+    pub fn in_file<S>(mut self, file: S) -> LoxError
+    where
+        S: Into<String>,
+    {
+        match &mut self {
+            LoxError::Scan(loc, _)
+            | LoxError::Parse(loc, _, _)
+            | LoxError::Resolve(loc, _, _)
+            | LoxError::Runtime(loc, ..)
+            | LoxError::Throw(loc, _, _)
+            | LoxError::Halt(loc, _, _)
+            | LoxError::Import(loc, _, _) => {
+                if loc.file.is_none() {
+                    loc.file = Some(file.into());
+                }
+            },
+            LoxError::Cast(..) | LoxError::Exit(_) => {},
+        }
+        self
+    }
+}
+
+/// Where an error was found relative to `token`, as shown after "Error".
+fn at(token: &Token) -> String {
+    if token.ty == TokenType::Eof {
+        " at end".into()
+    } else {
+        format!(" at \"{}\"", token.lexeme)
     }
 }
@@ -0,0 +1,441 @@
+use std::{
+    cell::RefCell,
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    mem,
+    rc::{
+        Rc,
+        Weak,
+    },
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
+
+use crate::*;
+
+/// An object with interior mutability that Lox values can form cycles
+/// through: scopes, instances, lists and maps.
+pub(crate) trait Trace {
+    /// Reports every reference this object holds to `tracer`.
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// Approximately how many bytes this object takes up, counting the
+    /// strings it holds.
+    fn size(&self) -> usize;
+
+    /// Drops everything this object refers to. Only called on objects that
+    /// are unreachable, to break the cycles keeping them alive.
+    fn clear(&mut self);
+}
+
+type Tracked = Weak<RefCell<dyn Trace>>;
+
+/// Counters describing the cycle collector's work.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Objects currently alive: scopes, instances, lists and maps.
+    pub live:            usize,
+    /// Objects allocated since the last collection.
+    pub allocated:       usize,
+    /// Collections run so far.
+    pub collections:     usize,
+    /// Objects freed by the last collection.
+    pub last_freed:      usize,
+    /// Objects freed by every collection so far.
+    pub total_freed:     usize,
+    /// Approximate bytes taken up by live objects and the strings they hold,
+    /// as of the last collection.
+    pub bytes:           usize,
+    /// Approximate bytes of objects, strings and closures allocated since the
+    /// last collection.
+    pub allocated_bytes: usize,
+}
+
+/// The memory used by one interpreter's scripts: their share of the bytes
+/// counted by `GcStats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Approximate bytes taken up by the live objects the scripts allocated
+    /// and the strings they hold, as of the last collection.
+    pub bytes:           usize,
+    /// Approximate bytes of objects, strings and closures the scripts
+    /// allocated since the last collection.
+    pub allocated_bytes: usize,
+}
+
+impl MemoryUsage {
+    /// Everything counted, including what was allocated since the last
+    /// collection whether it's been freed or not.
+    pub fn total(&self) -> usize {
+        self.bytes + self.allocated_bytes
+    }
+}
+
+/// Who allocated an object, for telling interpreters' memory apart. Each
+/// interpreter has its own; allocations made outside of any run belong to
+/// `HOST`.
+pub(crate) type Owner = usize;
+
+pub(crate) const HOST: Owner = 0;
+
+static NEXT_OWNER: AtomicUsize = AtomicUsize::new(HOST + 1);
+
+/// An owner no other interpreter has.
+pub(crate) fn new_owner() -> Owner {
+    NEXT_OWNER.fetch_add(1, Ordering::Relaxed)
+}
+
+const DEFAULT_THRESHOLD: usize = 1_000;
+
+/// Every tracked object ever allocated on this thread, weakly, with who
+/// allocated it. Objects
+/// aren't owned by the heap: reference counting still frees everything that
+/// isn't part of a cycle, and collections only look for cycles.
+struct Heap {
+    objects:   Vec<(Tracked, Owner)>,
+    /// Who's allocating now.
+    owner:     Owner,
+    usage:     HashMap<Owner, MemoryUsage>,
+    /// Allocations between automatic collections, if they're enabled.
+    threshold: Option<usize>,
+    /// The allocation count triggering the next automatic collection, which
+    /// grows with the heap so that collections stay proportional to it.
+    next:      usize,
+    /// The length at which `objects` is next pruned of freed objects, which
+    /// would otherwise pile up while automatic collections are off.
+    prune_at:  usize,
+    stats:     GcStats,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap {
+        objects:   Vec::new(),
+        owner:     HOST,
+        usage:     HashMap::new(),
+        threshold: Some(DEFAULT_THRESHOLD),
+        next:      DEFAULT_THRESHOLD,
+        prune_at:  DEFAULT_THRESHOLD,
+        stats:     GcStats::default(),
+    });
+}
+
+/// Registers a newly allocated object with the collector.
+pub(crate) fn track<T>(object: &Rc<RefCell<T>>)
+where
+    T: Trace + 'static,
+{
+    let weak: Tracked = Rc::downgrade(object) as Weak<RefCell<dyn Trace>>;
+    let size = RefCell::borrow(object).size();
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        let owner = heap.owner;
+        heap.objects.push((weak, owner));
+        heap.stats.allocated += 1;
+        heap.count(size);
+        if heap.objects.len() >= heap.prune_at {
+            heap.prune();
+        }
+    });
+}
+
+/// Counts `bytes` allocated for something other than a new tracked object:
+/// a string, a closure or a tracked object growing.
+pub(crate) fn allocate(bytes: usize) {
+    HEAP.with(|heap| heap.borrow_mut().count(bytes));
+}
+
+impl Heap {
+    /// Forgets the objects that have been freed, and waits for the number
+    /// left to double before doing so again.
+    fn prune(&mut self) {
+        self.objects.retain(|(weak, _)| weak.strong_count() > 0);
+        self.prune_at = DEFAULT_THRESHOLD.max(self.objects.len() * 2);
+    }
+
+    /// Counts `bytes` allocated by the current owner.
+    fn count(&mut self, bytes: usize) {
+        self.stats.allocated_bytes += bytes;
+        self.usage.entry(self.owner).or_default().allocated_bytes += bytes;
+    }
+}
+
+/// Runs `f` with what it allocates counted as `owner`'s.
+pub(crate) fn allocating_as<F, T>(owner: Owner, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let outer = HEAP.with(|heap| mem::replace(&mut heap.borrow_mut().owner, owner));
+    let res = f();
+    HEAP.with(|heap| heap.borrow_mut().owner = outer);
+    res
+}
+
+/// The memory used by what `owner` allocated.
+pub(crate) fn memory_usage(owner: Owner) -> MemoryUsage {
+    HEAP.with(|heap| heap.borrow().usage.get(&owner).cloned().unwrap_or_default())
+}
+
+/// The bytes taken up by the strings among `values`.
+pub(crate) fn strings_size<'v, I>(values: I) -> usize
+where
+    I: IntoIterator<Item = &'v Value>,
+{
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::Primitive(Primitive::String(string)) => string.len(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Runs a collection if enough objects have been allocated since the last
+/// one. Called by both backends at calls and loop iterations, where no
+/// tracked object is borrowed.
+pub(crate) fn maybe_collect() {
+    let due = HEAP.with(|heap| {
+        let heap = heap.borrow();
+        heap.threshold.is_some() && heap.stats.allocated >= heap.next
+    });
+    if due {
+        collect_garbage();
+    }
+}
+
+/// Frees every object that's only reachable from cycles of tracked objects,
+/// returning the updated stats. Collections also run automatically as
+/// objects are allocated.
+///
+/// Objects are tracked per thread, so this collects for every interpreter
+/// on the current thread.
+///
+/// Roots are found rather than enumerated: an object with more strong
+/// references than the tracked objects hold to it is referenced from
+/// outside the heap, by the interpreter's environment, a VM stack or frame,
+/// a native closure or the host. Everything reachable from a root survives.
+pub fn collect_garbage() -> GcStats {
+    let objects = HEAP.with(|heap| mem::take(&mut heap.borrow_mut().objects));
+    let mut tracer = Tracer::default();
+    let mut live = Vec::with_capacity(objects.len());
+    for (weak, owner) in objects {
+        if let Some(object) = weak.upgrade() {
+            // Discount the reference `upgrade` just made.
+            tracer.trace_object(&object, Rc::strong_count(&object) - 1, weak.clone());
+            live.push((weak, owner));
+        }
+    }
+    tracer.trace_pending();
+
+    let garbage = tracer.garbage();
+    let freed = garbage.len();
+    for object in garbage {
+        if let Some(object) = object.upgrade() {
+            if let Ok(mut object) = object.try_borrow_mut() {
+                object.clear();
+            }
+        }
+    }
+    live.retain(|(weak, _)| weak.strong_count() > 0);
+
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        // Anything allocated while clearing was registered in the meantime.
+        live.append(&mut heap.objects);
+        let mut usage: HashMap<Owner, MemoryUsage> = HashMap::new();
+        for (weak, owner) in &live {
+            usage.entry(*owner).or_default().bytes += object_size(weak);
+        }
+        heap.objects = live;
+        heap.prune_at = DEFAULT_THRESHOLD.max(heap.objects.len() * 2);
+        heap.stats.live = heap.objects.len();
+        heap.stats.allocated = 0;
+        heap.stats.bytes = usage.values().map(|usage| usage.bytes).sum();
+        heap.stats.allocated_bytes = 0;
+        heap.usage = usage;
+        heap.stats.collections += 1;
+        heap.stats.last_freed = freed;
+        heap.stats.total_freed += freed;
+        if let Some(threshold) = heap.threshold {
+            heap.next = threshold.max(heap.stats.live);
+        }
+        heap.stats
+    })
+}
+
+/// The size of a tracked object, or nothing if it's been freed. Objects
+/// borrowed elsewhere count as nothing, too.
+fn object_size(weak: &Tracked) -> usize {
+    let object = match weak.upgrade() {
+        Some(object) => object,
+        None => return 0,
+    };
+    let size = match object.try_borrow() {
+        Ok(object) => object.size(),
+        Err(_) => 0,
+    };
+    size
+}
+
+/// The collector's stats for the current thread, covering every interpreter
+/// on it.
+pub fn gc_stats() -> GcStats {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.stats.live =
+            heap.objects.iter().filter(|(weak, _)| weak.strong_count() > 0).count();
+        heap.stats
+    })
+}
+
+/// Sets how many objects are allocated between automatic collections, or
+/// turns them off with `None`. Like the objects themselves, the setting is
+/// shared by every interpreter on the current thread.
+pub fn set_gc_threshold(threshold: Option<usize>) {
+    HEAP.with(|heap| {
+        let mut heap = heap.borrow_mut();
+        heap.threshold = threshold;
+        heap.next = threshold.unwrap_or(0);
+    })
+}
+
+/// An object found while tracing: either a tracked object or a function or
+/// class, which can't form cycles by themselves but can be part of one.
+struct Node {
+    /// References to this object from anywhere.
+    strong:   usize,
+    /// References to this object from other nodes.
+    internal: usize,
+    edges:    Vec<usize>,
+    /// Set for tracked objects, which are cleared if they're garbage.
+    object:   Option<Tracked>,
+    /// Whether this node's references are unknown because it was borrowed
+    /// while tracing.
+    opaque:   bool,
+}
+
+enum Pending {
+    Fn(Rc<LoxFn>),
+    Class(Rc<LoxClass>),
+}
+
+/// Builds the graph of references between tracked objects.
+#[derive(Default)]
+pub(crate) struct Tracer {
+    nodes:   HashMap<usize, Node>,
+    current: usize,
+    pending: Vec<(usize, Pending)>,
+}
+
+impl Tracer {
+    pub fn value(&mut self, value: &Value) {
+        match value {
+            Value::LoxFn(function) => self.function(function),
+            Value::Class(class) => self.class(class),
+            Value::Instance(instance) => instance.trace(self),
+            Value::List(list) => list.trace(self),
+            Value::Map(map) => map.trace(self),
+            Value::Module(module) => module.trace(self),
+            Value::Primitive(_) | Value::RustFn(_) => {},
+        }
+    }
+
+    /// Records a reference to a tracked object.
+    pub fn object<T>(&mut self, object: &Rc<RefCell<T>>) {
+        let id = Rc::as_ptr(object) as *const () as usize;
+        self.edge(id);
+    }
+
+    pub fn function(&mut self, function: &Rc<LoxFn>) {
+        let id = Rc::as_ptr(function) as *const () as usize;
+        if self.discover(id, Rc::strong_count(function)) {
+            self.pending.push((id, Pending::Fn(function.clone())));
+        }
+        self.edge(id);
+    }
+
+    pub fn class(&mut self, class: &Rc<LoxClass>) {
+        let id = Rc::as_ptr(class) as *const () as usize;
+        if self.discover(id, Rc::strong_count(class)) {
+            self.pending.push((id, Pending::Class(class.clone())));
+        }
+        self.edge(id);
+    }
+
+    fn edge(&mut self, id: usize) {
+        if let Some(node) = self.nodes.get_mut(&self.current) {
+            node.edges.push(id);
+        }
+    }
+
+    /// Adds a node for `id` if it's new, returning whether it was.
+    fn discover(&mut self, id: usize, strong: usize) -> bool {
+        if self.nodes.contains_key(&id) {
+            return false;
+        }
+        self.nodes.insert(id, Node {
+            strong,
+            internal: 0,
+            edges: Vec::new(),
+            object: None,
+            opaque: false,
+        });
+        true
+    }
+
+    fn trace_object(&mut self, object: &Rc<RefCell<dyn Trace>>, strong: usize, weak: Tracked) {
+        let id = Rc::as_ptr(object) as *const () as usize;
+        self.discover(id, strong);
+        let node = self.nodes.get_mut(&id).unwrap();
+        node.object = Some(weak);
+        self.current = id;
+        match object.try_borrow() {
+            Ok(object) => object.trace(self),
+            Err(_) => self.nodes.get_mut(&id).unwrap().opaque = true,
+        }
+    }
+
+    fn trace_pending(&mut self) {
+        while let Some((id, pending)) = self.pending.pop() {
+            self.current = id;
+            match pending {
+                Pending::Fn(function) => function.trace(self),
+                Pending::Class(class) => class.trace(self),
+            }
+        }
+    }
+
+    /// The tracked objects unreachable from any root.
+    fn garbage(mut self) -> Vec<Tracked> {
+        let edges: Vec<usize> = self.nodes.values().flat_map(|node| node.edges.clone()).collect();
+        for id in edges {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.internal += 1;
+            }
+        }
+
+        let mut reachable: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.opaque || node.strong > node.internal)
+            .map(|(&id, _)| id)
+            .collect();
+        let mut marked = HashSet::new();
+        while let Some(id) = reachable.pop() {
+            if marked.insert(id) {
+                if let Some(node) = self.nodes.get(&id) {
+                    reachable.extend(node.edges.iter().cloned());
+                }
+            }
+        }
+
+        self.nodes
+            .into_iter()
+            .filter(|(id, _)| !marked.contains(id))
+            .filter_map(|(_, node)| node.object)
+            .collect()
+    }
+}
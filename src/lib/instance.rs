@@ -1,9 +1,8 @@
 use std::{
-    borrow::Borrow,
+    any::Any,
     cell::RefCell,
-    collections::HashMap,
     fmt,
-    hash::Hash,
+    mem,
     rc::Rc,
 };
 
@@ -25,42 +24,149 @@ type InstanceHandle = Rc<RefCell<InstanceInner>>;
 #[derive(Debug)]
 struct InstanceInner {
     class:  Rc<LoxClass>,
-    fields: HashMap<LoxStr, Value>,
+    fields: SymbolMap<Value>,
+    /// The Rust value wrapped by instances of native classes, set by the
+    /// native initializer.
+    data:   Option<NativeData>,
+}
+
+pub(crate) type NativeData = Rc<RefCell<dyn Any>>;
+
+impl Trace for InstanceInner {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.class(&self.class);
+        for value in self.fields.values() {
+            tracer.value(value);
+        }
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<InstanceInner>()
+            + self.fields.capacity() * mem::size_of::<(Symbol, Value)>()
+            + strings_size(self.fields.values())
+    }
+
+    fn clear(&mut self) {
+        self.fields.clear();
+        self.data = None;
+    }
 }
 
 impl fmt::Display for LoxInstance {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<instance {}>", RefCell::borrow(&self.inner).class.name)
+        let inner = RefCell::borrow(&self.inner);
+        let display = inner.class.native_members().and_then(|native| native.display.as_ref());
+        if let (Some(display), Some(data)) = (display, &inner.data) {
+            if let Ok(data) = data.try_borrow() {
+                if let Some(res) = display(&*data, f) {
+                    return res;
+                }
+            }
+        }
+        write!(f, "<instance {}>", inner.class.name)
     }
 }
 
 impl LoxInstance {
     pub fn new(class: Rc<LoxClass>) -> Self {
+        let inner = Rc::new(RefCell::new(InstanceInner {
+            class,
+            fields: Default::default(),
+            data: None,
+        }));
+        track(&inner);
         LoxInstance {
-            inner: Rc::new(RefCell::new(InstanceInner {
-                class,
-                fields: Default::default(),
-            })),
+            inner,
         }
     }
 
-    pub fn get<K>(&self, name: &K) -> Option<Value>
+    pub fn class_name(&self) -> LoxStr {
+        RefCell::borrow(&self.inner).class.name.clone()
+    }
+
+    /// Whether this is an instance of `class` or one of its subclasses.
+    pub fn is_instance_of(&self, class: &LoxClass) -> bool {
+        RefCell::borrow(&self.inner).class.is_subclass_of(class)
+    }
+
+    pub fn get<S>(&self, name: S) -> Option<Value>
     where
-        K: Eq + Hash,
-        LoxStr: Borrow<K>,
+        S: Into<Symbol>,
     {
+        let name = name.into();
         let borrowed = RefCell::borrow(&self.inner);
         borrowed
             .fields
-            .get(name)
+            .get(&name)
             .cloned()
-            .or_else(|| Some(Value::LoxFn(borrowed.class.find_method(self, name)?)))
+            .or_else(|| borrowed.class.find_method(self, name))
     }
 
-    pub fn set<K>(&self, name: K, value: Value) -> Option<Value>
+    /// The Lox method `name` to call on this instance, unless a field or
+    /// native getter of the same name shadows it.
+    pub(crate) fn invoked_method(&self, name: Symbol, cache: &MethodCache) -> Option<Rc<LoxFn>> {
+        let inner = RefCell::borrow(&self.inner);
+        if inner.fields.contains_key(&name) {
+            return None;
+        }
+        if inner.class.native_members().is_some_and(|native| native.getters.contains_key(&name)) {
+            return None;
+        }
+        cache.get(&inner.class, name)
+    }
+
+    /// Reads the property `name`: a field, a native getter or a bound method.
+    pub fn get_property(&self, name: Symbol) -> Result<Value, LoxError> {
+        let getter = {
+            let inner = RefCell::borrow(&self.inner);
+            if let Some(value) = inner.fields.get(&name) {
+                return Ok(value.clone());
+            }
+            inner.class.native_members().and_then(|native| native.getters.get(&name).cloned())
+        };
+        match getter {
+            Some(getter) => getter.call(self, vec![]),
+            None => self.get(name).ok_or_else(|| {
+                LoxError::typecast(format!("undefined field: {}", name)).with_kind(ErrorKind::Name)
+            }),
+        }
+    }
+
+    /// Writes the property `name`, through a native setter if there is one.
+    pub fn set_property(&self, name: Symbol, value: Value) -> Result<(), LoxError> {
+        let setter = RefCell::borrow(&self.inner)
+            .class
+            .native_members()
+            .and_then(|native| native.setters.get(&name).cloned());
+        match setter {
+            Some(setter) => setter.call(self, vec![value]).map(|_| ()),
+            None => {
+                self.set(name, value);
+                Ok(())
+            },
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.object(&self.inner);
+    }
+
+    pub(crate) fn native_data(&self) -> Option<NativeData> {
+        RefCell::borrow(&self.inner).data.clone()
+    }
+
+    pub(crate) fn set_native_data(&self, data: NativeData) {
+        self.inner.borrow_mut().data = Some(data);
+    }
+
+    pub fn set<S>(&self, name: S, value: Value) -> Option<Value>
     where
-        K: Into<LoxStr>,
+        S: Into<Symbol>,
     {
-        self.inner.borrow_mut().fields.insert(name.into(), value)
+        let old = self.inner.borrow_mut().fields.insert(name.into(), value);
+        if old.is_none() {
+            allocate(mem::size_of::<(Symbol, Value)>());
+        }
+        old
     }
 }
@@ -2,12 +2,68 @@ use crate::*;
 
 use std::{
     collections::HashMap,
+    io::{
+        self,
+        Write,
+    },
     mem::swap,
+    path::PathBuf,
+    rc::Rc,
     time,
 };
 
+/// How a statement finished, when it didn't simply fall through to the next
+/// one.
+#[derive(Debug)]
+pub enum Flow {
+    Return(Value),
+    Break,
+    Continue,
+}
+
+/// Lox source run by every new `Interpreter` to define the error classes.
+const PRELUDE: &str = "
+class Error {
+    init(message) {
+        this.message = message;
+    }
+}
+class RuntimeError < Error {}
+class TypeError < RuntimeError {}
+class NameError < RuntimeError {}
+class ArityError < RuntimeError {}
+class IndexError < RuntimeError {}
+";
+
 pub struct Interpreter {
-    pub environment: Environment,
+    pub environment:     Environment,
+    pub frames:          Vec<CallFrame>,
+    /// The value of the `throw` in flight, if any.
+    pub thrown:          Option<Value>,
+    /// The backend imported modules are run with.
+    pub backend:         Backend,
+    /// What scripts may do outside the interpreter.
+    pub capabilities:    Capabilities,
+    /// How much work scripts may do.
+    pub limits:          Limits,
+    /// What scripts are warned about before they run.
+    pub lints:           Lints,
+    /// How numbers are printed and converted to strings while scripts run.
+    pub number_format:   NumberFormat,
+    pub(crate) budget:   Budget,
+    /// Where `print` writes.
+    pub(crate) stdout:   Box<dyn Write>,
+    /// Where errors are reported by the REPL.
+    pub(crate) stderr:   Box<dyn Write>,
+    /// The prelude's class for each kind of runtime error.
+    error_classes:       HashMap<ErrorKind, Rc<LoxClass>>,
+    /// Natives and the prelude, shared by the globals of every module.
+    pub(crate) builtins: Environment,
+    /// The files being executed, innermost last.
+    pub(crate) files:    Vec<PathBuf>,
+    pub(crate) modules:  HashMap<PathBuf, LoxModule>,
+    /// Who the memory this interpreter's scripts allocate is counted for.
+    pub(crate) owner:    Owner,
 }
 
 impl Interpreter {
@@ -15,7 +71,8 @@ impl Interpreter {
         self.visit(expr)
     }
 
-    pub fn execute(&mut self, stmt: &Stmt) -> Result<Option<Value>, LoxError> {
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<Option<Flow>, LoxError> {
+        self.tick(|| stmt.span())?;
         self.visit(stmt)
     }
 
@@ -23,30 +80,133 @@ impl Interpreter {
         &mut self,
         name: &Token,
         value: Value,
-        depth: Option<usize>,
+        slot: Option<Slot>,
     ) -> Result<(), LoxError> {
-        let res = if let Some(depth) = depth {
-            self.environment.ancestor(depth).and_then(|mut e| e.assign(&name.lexeme, value))
-        } else {
-            self.environment.assign_global(&name.lexeme, value)
-        };
-        if res.is_none() {
-            Err(LoxError::runtime(name, format!("variable {} is not defined", name.lexeme)))
+        if self.environment.assign_at(name.symbol, value, slot).is_none() {
+            let message = format!("variable {} is not defined", name.lexeme);
+            Err(LoxError::runtime(name, message).with_kind(ErrorKind::Name))
         } else {
             Ok(())
         }
     }
 
-    pub fn define(&mut self, name: &Token, value: Value) {
-        self.environment.define(name.lexeme.clone(), value)
+    /// Calls `callee` from `call_site`, keeping a frame for it on the call
+    /// stack so that runtime errors raised inside carry a stack trace.
+    pub fn call(
+        &mut self,
+        callee: Value,
+        args: Vec<Value>,
+        call_site: Span,
+    ) -> Result<Value, LoxError> {
+        let callable = callee.callable().map_err(|e| e.or_at(call_site))?;
+        self.check_arity(&callee, args.len(), call_site)?;
+        self.in_frame(callee.clone(), Some(call_site), |interp| callable.call(interp, args))
     }
 
-    pub fn get_var_at(&mut self, name: &Token, depth: Option<usize>) -> Option<Value> {
-        if let Some(depth) = depth {
-            self.environment.ancestor(depth).and_then(|e| e.get(&name.lexeme))
-        } else {
-            self.environment.get_global(&name.lexeme)
+    /// Calls the method `method` on `this` without binding it first.
+    pub fn call_method(
+        &mut self,
+        this: LoxInstance,
+        method: Rc<LoxFn>,
+        args: Vec<Value>,
+        call_site: Span,
+    ) -> Result<Value, LoxError> {
+        let callee = Value::LoxFn(method.clone());
+        self.check_arity(&callee, args.len(), call_site)?;
+        self.in_frame(callee, Some(call_site), |interp| method.call_method(interp, this, args))
+    }
+
+    /// Runs `f` in a frame for `callee`, called from `call_site` or, if that
+    /// is `None`, from host code.
+    fn in_frame<F>(
+        &mut self,
+        callee: Value,
+        call_site: Option<Span>,
+        f: F,
+    ) -> Result<Value, LoxError>
+    where
+        F: FnOnce(&mut Interpreter) -> Result<Value, LoxError>,
+    {
+        let span = call_site.unwrap_or_default();
+        self.check_depth(span)?;
+        maybe_collect();
+        self.check_memory(span)?;
+
+        self.frames.push(CallFrame {
+            callee,
+            call_site,
+        });
+        let res = f(self).map_err(|e| e.or_at(span).with_trace(&self.frames));
+        self.frames.pop();
+        res
+    }
+
+    pub fn check_arity(
+        &self,
+        callee: &Value,
+        n_args: usize,
+        call_site: Span,
+    ) -> Result<(), LoxError> {
+        let arity = callee.callable()?.arity();
+        if n_args != arity {
+            return Err(LoxError::runtime_at(
+                call_site,
+                format!("expected {} arguments but got {}", arity, n_args),
+            )
+            .with_kind(ErrorKind::Arity));
+        }
+        Ok(())
+    }
+
+    /// Throws `value` from `span`, returning the error that carries it up to
+    /// the nearest `catch`.
+    pub fn throw(&mut self, span: Span, value: Value) -> LoxError {
+        let description = match &value {
+            Value::Instance(instance) => match instance.get("message") {
+                Some(message) => format!("{}: {}", instance.class_name(), message),
+                None => value.to_string(),
+            },
+            value => value.to_string(),
+        };
+        self.thrown = Some(value);
+        LoxError::throw(span, description)
+    }
+
+    /// The value a `catch` clause receives for `err`: the thrown value, or an
+    /// instance of the `RuntimeError` subclass for its `ErrorKind` describing
+    /// an error raised by the interpreter itself.
+    pub fn error_value(&mut self, err: &LoxError) -> Value {
+        if let LoxError::Throw(..) = err {
+            return self.thrown.take().unwrap_or_else(|| Primitive::Nil.into());
         }
+        let message = match err {
+            LoxError::Scan(_, msg)
+            | LoxError::Parse(_, _, msg)
+            | LoxError::Cast(msg, _)
+            | LoxError::Runtime(_, msg, ..)
+            | LoxError::Throw(_, msg, _)
+            | LoxError::Halt(_, msg, _) => msg.clone(),
+            LoxError::Resolve(_, _, kind) => kind.to_string(),
+            LoxError::Import(_, path, _) => format!("could not import {}", path),
+            LoxError::Exit(_) => err.to_string(),
+        };
+        let line = match err.span() {
+            Some(span) => Primitive::Number(span.line as f64),
+            None => Primitive::Nil,
+        };
+        let kind = err.kind().unwrap_or(ErrorKind::Runtime);
+        let error = LoxInstance::new(self.error_classes[&kind].clone());
+        error.set("message", Primitive::String(message.into()).into());
+        error.set("line", line.into());
+        Value::Instance(error)
+    }
+
+    pub fn define(&mut self, name: &Token, value: Value) {
+        self.environment.define(name.symbol, value)
+    }
+
+    pub fn get_var_at(&mut self, name: &Token, slot: Option<Slot>) -> Option<Value> {
+        self.environment.get_at(name.symbol, slot)
     }
 }
 
@@ -55,112 +215,66 @@ impl<'a, 's> Visitor<&'a Expr> for Interpreter {
 
     fn visit(&mut self, expr: &'a Expr) -> Self::Output {
         Ok(match expr {
-            Expr::Assign(name, value, depth) => {
+            Expr::Assign(name, value, slot) => {
                 let value = self.evaluate(&*value)?;
-                self.assign_at(&name, value.clone(), *depth)?;
+                self.assign_at(name, value.clone(), *slot)?;
                 value
             },
             Expr::Binary(left, op, right) => {
                 let left = self.evaluate(&*left)?;
                 let right = self.evaluate(&*right)?;
 
-                match op.ty {
-                    TokenType::Minus => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Number(left - right)
-                    },
-                    TokenType::Slash => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Number(left / right)
-                    },
-                    TokenType::Star => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Number(left * right)
-                    },
-                    TokenType::Plus => {
-                        if let (Ok(left), Ok(right)) = (
-                            left.primitive().and_then(|p| p.number()),
-                            right.primitive().and_then(|p| p.number()),
-                        ) {
-                            Primitive::Number(left + right).into()
-                        } else if let (Ok(mut left), Ok(right)) = (
-                            left.primitive().and_then(|p| p.string()).map(Clone::clone),
-                            right.primitive().and_then(|p| p.string()),
-                        ) {
-                            left.push_tendril(right);
-                            Primitive::String(left).into()
-                        } else {
-                            return Err(LoxError::runtime(
-                                &op,
-                                "requires two numbers or two strings",
-                            ));
-                        }
-                    },
-                    TokenType::Greater => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Bool(left > right).into()
-                    },
-                    TokenType::GreaterEqual => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Bool(left >= right).into()
-                    },
-                    TokenType::Less => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Bool(left < right).into()
-                    },
-                    TokenType::LessEqual => {
-                        let (left, right) = number_operands(&op, left, right)?;
-                        Primitive::Bool(left <= right).into()
-                    },
-                    TokenType::BangEqual => Primitive::Bool(!is_equal(left, right)).into(),
-                    TokenType::EqualEqual => Primitive::Bool(is_equal(left, right)).into(),
-                    _ => {
-                        return Err(LoxError::runtime(
-                            &op,
-                            format!("unexpected token type: {:?}", op.ty),
-                        ))
-                    },
-                }
-                .into()
+                binary(op.ty, left, right).map_err(|e| e.or_at(expr.span()))?
             },
-            Expr::Call(callee, paren, args) => {
-                let callee = self.evaluate(&*callee)?;
+            Expr::Call(callee, paren, args, cache) => {
+                let callee = match &**callee {
+                    Expr::Get(object, name) => {
+                        let object = self.evaluate(object)?;
+                        get_method(object, name.symbol, cache).map_err(|e| e.or_at(name.span))?
+                    },
+                    callee => Callee::Value(self.evaluate(callee)?),
+                };
 
                 let args = args
                     .into_iter()
                     .map(|arg| self.evaluate(&arg))
                     .collect::<Result<Vec<_>, _>>()?;
 
-                let function = callee.callable()?;
-
-                let n_args = args.len();
-                let arity = function.arity();
-                if n_args != arity {
-                    return Err(LoxError::runtime(
-                        paren,
-                        format!("expected {} arguments but got {}", arity, n_args),
-                    ));
+                match callee {
+                    Callee::Value(callee) => self.call(callee, args, paren.span)?,
+                    Callee::Method(this, method) => {
+                        self.call_method(this, method, args, paren.span)?
+                    },
                 }
-
-                function.call(self, args)?
             },
-            Expr::Grouping(e) => return self.evaluate(e),
+            Expr::Grouping(e, _) => return self.evaluate(e),
             Expr::Get(expr, name) => {
                 let object = self.evaluate(&*expr)?;
-                if let Value::Instance(instance) = object {
-                    instance.get(&name.lexeme).ok_or_else(|| {
-                        LoxError::runtime(name, format!("undefined field: {}", name.lexeme))
-                    })?
-                } else {
-                    return Err(LoxError::runtime(name, "only instances have fields"));
+                get_property(object, name.symbol).map_err(|e| e.or_at(name.span))?
+            },
+            Expr::Index(object, bracket, index) => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                get_index(object, index).map_err(|e| e.or_at(bracket.span))?
+            },
+            Expr::List(elements, _) => Value::List(LoxList::new(
+                elements.iter().map(|e| self.evaluate(e)).collect::<Result<Vec<_>, _>>()?,
+            )),
+            Expr::Literal(v, _) => v.clone().into(),
+            Expr::Map(entries, span) => {
+                let map = LoxMap::new();
+                for (key, value) in entries {
+                    let key = self.evaluate(key)?;
+                    let value = self.evaluate(value)?;
+                    map.set(&key, value).map_err(|e| e.or_at(*span))?;
                 }
+                Value::Map(map)
             },
-            Expr::Literal(v) => v.clone().into(),
             Expr::Logical(left, op, right) => {
                 let left = self.evaluate(&*left)?;
 
                 match op.ty {
-                    TokenType::And if is_truthy(&left) => left.clone(),
+                    TokenType::And if !is_truthy(&left) => left.clone(),
                     TokenType::Or if is_truthy(&left) => left.clone(),
                     _ => self.evaluate(&*right)?,
                 }
@@ -169,16 +283,26 @@ impl<'a, 's> Visitor<&'a Expr> for Interpreter {
                 let object = self.evaluate(&*object)?;
                 if let Value::Instance(instance) = object {
                     let value = self.evaluate(&*value)?;
-                    instance.set(name.lexeme.clone(), value.clone());
+                    instance
+                        .set_property(name.symbol, value.clone())
+                        .map_err(|e| e.or_at(name.span))?;
                     value
                 } else {
-                    return Err(LoxError::runtime(name, "only instances have fields"));
+                    let err = LoxError::runtime(name, "only instances have fields");
+                    return Err(err.with_kind(ErrorKind::Type));
                 }
             },
-            Expr::Super(kw, method, depth) => {
+            Expr::SetIndex(object, bracket, index, value) => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+                set_index(object, index, value.clone()).map_err(|e| e.or_at(bracket.span))?;
+                value
+            },
+            Expr::Super(kw, method, slot) => {
                 let superclass = self
                     .environment
-                    .get_at("super".as_bytes(), *depth)
+                    .get_at(kw.symbol, *slot)
                     .map(|v| {
                         if let Value::Class(v) = v {
                             Ok(v)
@@ -191,7 +315,7 @@ impl<'a, 's> Visitor<&'a Expr> for Interpreter {
                     })??;
                 let this = self
                     .environment
-                    .get_at("this".as_bytes(), depth.map(|d| d - 1))
+                    .get_at(Symbol::THIS, slot.map(Slot::this))
                     .map(|v| {
                         if let Value::Instance(v) = v {
                             Ok(v)
@@ -202,35 +326,30 @@ impl<'a, 's> Visitor<&'a Expr> for Interpreter {
                     .ok_or_else(|| {
                         LoxError::runtime(kw, "could not find 'this' (interpreter bug)")
                     })??;
-                superclass.find_method(&this, &method.lexeme).map(|f| Value::LoxFn(f)).ok_or_else(
-                    || LoxError::runtime(method, format!("undefined method: {}", method.lexeme)),
-                )?
+                superclass.find_method(&this, method.symbol).ok_or_else(|| {
+                    LoxError::runtime(method, format!("undefined method: {}", method.lexeme))
+                        .with_kind(ErrorKind::Name)
+                })?
             },
-            Expr::This(this, depth) => {
-                self.get_var_at(this, *depth).unwrap_or_else(|| Primitive::Nil.into())
+            Expr::This(this, slot) => {
+                self.get_var_at(this, *slot).unwrap_or_else(|| Primitive::Nil.into())
             },
             Expr::Unary(op, right) => {
                 let right = self.evaluate(&*right)?;
-                match op.ty {
-                    TokenType::Minus => {
-                        Primitive::Number(-*right.primitive().and_then(|p| p.number())?)
-                    },
-                    TokenType::Bang => Primitive::Bool(!is_truthy(&right)),
-                    _ => unreachable!(),
-                }
-                .into()
+                unary(op.ty, right).map_err(|e| e.or_at(op.span))?
             },
-            Expr::Variable(name, depth) => self
-                .get_var_at(&name, *depth)
+            Expr::Variable(name, slot) => self
+                .get_var_at(name, *slot)
                 .ok_or_else(|| {
                     LoxError::runtime(&name, format!("Undefined variable: {}", name.lexeme))
+                        .with_kind(ErrorKind::Name)
                 })?
                 .clone(),
         })
     }
 }
 
-fn is_truthy(v: &Value) -> bool {
+pub fn is_truthy(v: &Value) -> bool {
     match v.primitive() {
         Ok(Primitive::Nil) => false,
         Ok(Primitive::Bool(b)) => *b,
@@ -238,30 +357,150 @@ fn is_truthy(v: &Value) -> bool {
     }
 }
 
-fn is_equal(left: Value, right: Value) -> bool {
+pub fn is_equal(left: Value, right: Value) -> bool {
     left == right
 }
 
-fn number_operand(op: &Token, right: Value) -> Result<f64, LoxError> {
-    Ok(*right
-        .primitive()
-        .and_then(|p| p.number())
-        .map_err(|e| LoxError::runtime(op, format!("{}", e)))?)
+fn number_operands(left: Value, right: Value) -> Result<(f64, f64), LoxError> {
+    Ok((*left.primitive()?.number()?, *right.primitive()?.number()?))
+}
+
+/// Reads the property `name` of `object`: a field or method of an instance,
+/// or one of the native methods of a built-in type.
+pub fn get_property(object: Value, name: Symbol) -> Result<Value, LoxError> {
+    match object {
+        Value::Instance(instance) => instance.get_property(name),
+        Value::List(list) => list
+            .method(&name.name())
+            .ok_or_else(|| undefined(format!("undefined list method: {}", name))),
+        Value::Map(map) => map
+            .method(&name.name())
+            .ok_or_else(|| undefined(format!("undefined map method: {}", name))),
+        Value::Module(module) => module
+            .get(name)
+            .ok_or_else(|| undefined(format!("module {} has no member {}", module.name, name))),
+        Value::Primitive(Primitive::String(string)) => string_method(&string, &name.name())
+            .ok_or_else(|| undefined(format!("undefined string method: {}", name))),
+        _ => Err(LoxError::typecast("only instances have fields")),
+    }
+}
+
+fn undefined(message: String) -> LoxError {
+    LoxError::typecast(message).with_kind(ErrorKind::Name)
+}
+
+/// What a call calls: a value, or a Lox method of an instance, which is
+/// called without binding it first.
+pub enum Callee {
+    Value(Value),
+    Method(LoxInstance, Rc<LoxFn>),
+}
+
+/// Looks up the property `name` of `object` to call it, as `get_property`
+/// but leaving Lox methods of instances unbound.
+pub fn get_method(object: Value, name: Symbol, cache: &MethodCache) -> Result<Callee, LoxError> {
+    if let Value::Instance(instance) = &object {
+        if let Some(method) = instance.invoked_method(name, cache) {
+            return Ok(Callee::Method(instance.clone(), method));
+        }
+    }
+    get_property(object, name).map(Callee::Value)
+}
+
+pub fn get_index(object: Value, index: Value) -> Result<Value, LoxError> {
+    match object {
+        Value::List(list) => list.get(&index),
+        Value::Map(map) => map.get(&index),
+        Value::Primitive(Primitive::String(string)) => string_index(&string, &index),
+        _ => Err(LoxError::typecast("only lists, maps and strings can be indexed")),
+    }
+}
+
+pub fn set_index(object: Value, index: Value, value: Value) -> Result<(), LoxError> {
+    match object {
+        Value::List(list) => list.set(&index, value),
+        Value::Map(map) => map.set(&index, value),
+        Value::Primitive(Primitive::String(_)) => Err(LoxError::typecast("strings are immutable")),
+        _ => Err(LoxError::typecast("only lists and maps can be indexed")),
+    }
 }
 
-fn number_operands(op: &Token, left: Value, right: Value) -> Result<(f64, f64), LoxError> {
-    Ok((number_operand(op, left)?, number_operand(op, right)?))
+/// Applies a binary operator to two evaluated operands. Shared by both
+/// backends; callers attach the operator's location to any error.
+pub fn binary(ty: TokenType, left: Value, right: Value) -> Result<Value, LoxError> {
+    Ok(match ty {
+        TokenType::Minus => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Number(left - right)
+        },
+        TokenType::Slash => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Number(left / right)
+        },
+        TokenType::Star => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Number(left * right)
+        },
+        TokenType::Plus => {
+            if let (Ok(left), Ok(right)) = (
+                left.primitive().and_then(|p| p.number()),
+                right.primitive().and_then(|p| p.number()),
+            ) {
+                Primitive::Number(left + right)
+            } else if let (Ok(mut left), Ok(right)) = (
+                left.primitive().and_then(|p| p.string()).cloned(),
+                right.primitive().and_then(|p| p.string()),
+            ) {
+                left.push_tendril(right);
+                allocate(left.len());
+                Primitive::String(left)
+            } else {
+                return Err(LoxError::typecast("requires two numbers or two strings"));
+            }
+        },
+        TokenType::Greater => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Bool(left > right)
+        },
+        TokenType::GreaterEqual => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Bool(left >= right)
+        },
+        TokenType::Less => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Bool(left < right)
+        },
+        TokenType::LessEqual => {
+            let (left, right) = number_operands(left, right)?;
+            Primitive::Bool(left <= right)
+        },
+        TokenType::BangEqual => Primitive::Bool(!is_equal(left, right)),
+        TokenType::EqualEqual => Primitive::Bool(is_equal(left, right)),
+        _ => return Err(LoxError::typecast(format!("unexpected token type: {:?}", ty))),
+    }
+    .into())
+}
+
+pub fn unary(ty: TokenType, right: Value) -> Result<Value, LoxError> {
+    Ok(match ty {
+        TokenType::Minus => Primitive::Number(-*right.primitive().and_then(|p| p.number())?),
+        TokenType::Bang => Primitive::Bool(!is_truthy(&right)),
+        _ => unreachable!(),
+    }
+    .into())
 }
 
 impl<'a, 's> Visitor<&'a Stmt> for Interpreter {
-    type Output = Result<Option<Value>, LoxError>;
+    type Output = Result<Option<Flow>, LoxError>;
     fn visit(&mut self, stmt: &'a Stmt) -> Self::Output {
         match stmt {
-            Stmt::Block(stmts) => {
+            Stmt::Block(stmts, _) => {
                 let new_env = Environment::with_enclosing(&self.environment);
                 let res = self.with_env(new_env, |interp| interp.execute_block(stmts));
                 return res;
             },
+            Stmt::Break(_) => return Ok(Some(Flow::Break)),
+            Stmt::Continue(_) => return Ok(Some(Flow::Continue)),
             Stmt::Class(name, superclass, body) => {
                 let superclass = superclass
                     .as_ref()
@@ -275,21 +514,21 @@ impl<'a, 's> Visitor<&'a Stmt> for Interpreter {
                         if let Value::Class(sc) = sc {
                             Ok(sc)
                         } else {
-                            Err(LoxError::runtime(&name, "superclass must be a class"))
+                            Err(LoxError::runtime(&name, "superclass must be a class")
+                                .with_kind(ErrorKind::Type))
                         }
                     })
                     .transpose()?;
-                self.environment.define(name.lexeme.clone(), Primitive::Nil.into());
                 let mut class_environment = self.environment.clone();
                 if let Some(superclass) = superclass.clone() {
                     class_environment = Environment::with_enclosing(&class_environment);
-                    class_environment.define("super", Value::Class(superclass));
+                    class_environment.define(Symbol::SUPER, Value::Class(superclass));
                 }
-                let mut methods = HashMap::new();
+                let mut methods = SymbolMap::default();
                 for method in body {
                     if let Stmt::Function(name, params, body) = method {
                         methods.insert(
-                            name.lexeme.clone(),
+                            name.symbol,
                             LoxFn::new(
                                 name,
                                 params,
@@ -304,7 +543,7 @@ impl<'a, 's> Visitor<&'a Stmt> for Interpreter {
                     }
                 }
                 let class = LoxClass::new(name.lexeme.clone(), superclass, methods);
-                self.environment.assign(&name.lexeme, Value::Class(class.into()));
+                self.define(name, Value::Class(class.into()));
             },
             Stmt::Expr(expr) => {
                 self.evaluate(expr)?;
@@ -315,33 +554,78 @@ impl<'a, 's> Visitor<&'a Stmt> for Interpreter {
                     LoxFn::new(name, params, body, self.environment.clone(), false).into(),
                 ),
             ),
-            Stmt::If(cond, then, otherwise) => {
+            Stmt::If(cond, then, otherwise, _) => {
                 if is_truthy(&self.evaluate(cond)?) {
                     return self.execute(&*then);
                 } else if let Some(otherwise) = otherwise {
                     return self.execute(&*otherwise);
                 }
             },
-            Stmt::Print(expr) => {
-                println!("{}", self.evaluate(expr)?);
+            Stmt::Print(expr, span) => {
+                let value = self.evaluate(expr)?;
+                self.print(*span, &value)?;
             },
             Stmt::Return(_, expr) => {
                 if let Some(expr) = expr {
                     let value = self.evaluate(expr)?;
-                    return Ok(Some(value));
+                    return Ok(Some(Flow::Return(value)));
                 } else {
-                    return Ok(Some(Primitive::Nil.into()));
+                    return Ok(Some(Flow::Return(Primitive::Nil.into())));
+                }
+            },
+            Stmt::Import(_, path, name) => {
+                let module = self.import(path.span, &path.literal.to_string())?;
+                self.define(name, Value::Module(module));
+            },
+            Stmt::Throw(kw, value) => {
+                let value = self.evaluate(value)?;
+                return Err(self.throw(kw.span, value));
+            },
+            Stmt::Try(_, body, catch, finally) => {
+                let scope = Environment::with_enclosing(&self.environment);
+                let mut res = self.with_env(scope, |interp| interp.execute_block(body));
+
+                if let Err(err) = &res {
+                    if !err.is_catchable() {
+                        return res;
+                    }
                 }
+                if let (Err(err), Some((name, handler))) = (&res, catch) {
+                    let mut scope = Environment::with_enclosing(&self.environment);
+                    scope.define(name.symbol, self.error_value(err));
+                    res = self.with_env(scope, |interp| interp.execute_block(handler));
+                }
+
+                if let Some(finally) = finally {
+                    // A jump or error out of the finally block replaces
+                    // whatever the try was going to do.
+                    let thrown = self.thrown.take();
+                    let scope = Environment::with_enclosing(&self.environment);
+                    if let Some(flow) =
+                        self.with_env(scope, |interp| interp.execute_block(finally))?
+                    {
+                        return Ok(Some(flow));
+                    }
+                    self.thrown = thrown;
+                }
+                return res;
             },
             Stmt::Var(name, expr) => {
                 let value = self.evaluate(expr)?;
                 self.define(&name, value);
             },
-            Stmt::While(cond, body) => {
+            Stmt::While(cond, body, inc, span) => {
                 while is_truthy(&self.evaluate(cond)?) {
-                    if let Some(ret) = self.execute(&*body)? {
-                        return Ok(Some(ret));
+                    match self.execute(body)? {
+                        Some(Flow::Break) => break,
+                        Some(Flow::Continue) | None => {},
+                        ret => return Ok(ret),
+                    }
+                    if let Some(inc) = inc {
+                        self.evaluate(inc)?;
                     }
+                    maybe_collect();
+                    self.check_memory(*span)?;
                 }
             },
         }
@@ -357,26 +641,81 @@ impl Default for Interpreter {
 }
 
 impl Interpreter {
-    fn new() -> Interpreter {
+    /// Creates an interpreter with only the natives and prelude defined.
+    pub fn new() -> Interpreter {
+        let owner = new_owner();
+        allocating_as(owner, || Interpreter::with_owner(owner))
+    }
+
+    fn with_owner(owner: Owner) -> Interpreter {
+        let builtins = Environment::new();
         let mut interp = Interpreter {
-            environment: Environment::new(),
+            environment:   builtins.clone(),
+            frames:        vec![],
+            thrown:        None,
+            backend:       Backend::TreeWalk,
+            capabilities:  Capabilities::default(),
+            limits:        Limits::default(),
+            lints:         Lints::default(),
+            number_format: NumberFormat::default(),
+            budget:        Budget::default(),
+            stdout:        Box::new(io::stdout()),
+            stderr:        Box::new(io::stderr()),
+            error_classes: HashMap::new(),
+            builtins,
+            files:         vec![],
+            modules:       HashMap::new(),
+            owner,
         };
-        interp.environment.define(
-            "clock",
-            Value::RustFn(
-                RustFn::new(0, |_, _| {
-                    Ok(Primitive::Number(
-                        time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap().as_secs()
-                            as f64,
-                    )
-                    .into())
-                })
-                .into(),
-            ),
-        );
+        interp.register("clock", || {
+            time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap().as_secs() as f64
+        });
+        interp.register("num", to_number);
+        interp.register("str", |value: Value| value.to_string());
+        interp.register("isInstance", is_instance);
+        interp.builtins.define_global("math", Value::Module(math_module()));
+        interp.register_io();
+
+        let mut resolver = Resolver::new();
+        let tokens = scan(PRELUDE).map(|token| token.expect("invalid prelude"));
+        let stmts: Vec<_> = Parser::new(tokens)
+            .map(|stmt| resolver.analyze(stmt.expect("invalid prelude")))
+            .collect();
+        resolver.into_reporter().finish().expect("invalid prelude");
+        for stmt in stmts {
+            interp.execute(&stmt).expect("invalid prelude");
+        }
+        for &kind in &ErrorKind::ALL {
+            match interp.environment.get(kind.to_string().as_str()) {
+                Some(Value::Class(class)) => interp.error_classes.insert(kind, class),
+                _ => panic!("prelude does not define {}", kind),
+            };
+        }
+        interp.environment = interp.builtins.new_module();
         interp
     }
-    pub fn execute_block(&mut self, stmts: &Vec<Stmt>) -> Result<Option<Value>, LoxError> {
+
+    /// Runs `source` as a script, keeping any globals it defines.
+    pub fn eval(&mut self, source: &str) -> Result<(), Errors<LoxError>> {
+        let backend = self.backend;
+        run(false, backend, self, None, source)
+    }
+
+    /// Looks up the global `name`.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.environment.get_global(name)
+    }
+
+    /// Calls `function` from host code.
+    pub fn call_function(&mut self, function: &Value, args: Vec<Value>) -> Result<Value, LoxError> {
+        self.limited(|interp| {
+            interp.check_arity(function, args.len(), Span::default())?;
+            let callable = function.callable()?;
+            interp.in_frame(function.clone(), None, |interp| callable.call(interp, args))
+        })
+    }
+
+    pub fn execute_block(&mut self, stmts: &[Stmt]) -> Result<Option<Flow>, LoxError> {
         for stmt in stmts.iter() {
             if let Some(ret) = self.execute(stmt)? {
                 return Ok(Some(ret));
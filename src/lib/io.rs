@@ -0,0 +1,202 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    fs,
+    io::{
+        self,
+        BufRead,
+        Write,
+    },
+    mem,
+    rc::Rc,
+};
+
+use crate::*;
+
+/// What scripts run by an `Interpreter` may do outside of it. Embedders
+/// running untrusted scripts can turn these off; natives that need a
+/// disabled capability raise a runtime error when called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `readLine()`.
+    pub stdin:      bool,
+    /// `readFile()`, `writeFile()`, `appendFile()`, `listDir()` and `import`.
+    pub filesystem: bool,
+    /// `exit()`.
+    pub exit:       bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Capabilities {
+            stdin:      true,
+            filesystem: true,
+            exit:       true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Capabilities {
+            stdin:      false,
+            filesystem: false,
+            exit:       false,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities::all()
+    }
+}
+
+/// A writer collecting output in memory, which can be handed to the
+/// interpreter while a clone is kept to read what was written.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer {
+    inner: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        OutputBuffer::default()
+    }
+
+    /// Everything written so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&RefCell::borrow(&self.inner)).into_owned()
+    }
+
+    /// Empties the buffer, returning what was in it.
+    pub fn take(&self) -> String {
+        let bytes = self.inner.replace(Vec::new());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Interpreter {
+    /// Sends `print` output to `output`, returning the previous writer.
+    pub fn set_output<W>(&mut self, output: W) -> Box<dyn Write>
+    where
+        W: Write + 'static,
+    {
+        mem::replace(&mut self.stdout, Box::new(output))
+    }
+
+    /// Sends error reports to `output`, returning the previous writer.
+    pub fn set_error_output<W>(&mut self, output: W) -> Box<dyn Write>
+    where
+        W: Write + 'static,
+    {
+        mem::replace(&mut self.stderr, Box::new(output))
+    }
+
+    /// Writes `value` and a newline to the output, for `print`.
+    pub fn print(&mut self, span: Span, value: &Value) -> Result<(), LoxError> {
+        writeln!(self.stdout, "{}", value)
+            .map_err(|e| LoxError::runtime_at(span, format!("could not write output: {}", e)))
+    }
+
+    /// Writes a report of `err` to the error output.
+    pub fn report(&mut self, err: &dyn fmt::Display) -> io::Result<()> {
+        self.stdout.flush()?;
+        writeln!(self.stderr, "{}", err)
+    }
+
+    /// Defines the natives for reading input, touching files and exiting.
+    pub(crate) fn register_io(&mut self) {
+        let read_line = RustFn::new("readLine", 0, |interp: &mut Interpreter, _| {
+            if !interp.capabilities.stdin {
+                return Err(disabled("readLine"));
+            }
+            let read = |interp: &mut Interpreter| -> io::Result<Value> {
+                // Prompts are usually printed without a newline.
+                interp.stdout.flush()?;
+                let mut line = String::new();
+                if io::stdin().lock().read_line(&mut line)? == 0 {
+                    return Ok(Primitive::Nil.into());
+                }
+                let len = line.trim_end_matches(&['\n', '\r'][..]).len();
+                line.truncate(len);
+                Ok(line.into())
+            };
+            read(interp).map_err(|e| {
+                LoxError::typecast(format!("could not read stdin: {}", e))
+                    .with_kind(ErrorKind::Runtime)
+            })
+        });
+        self.builtins.define_global("readLine", Value::RustFn(read_line.into()));
+        self.register_guarded("readFile", |caps| caps.filesystem, |path: String| {
+            fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path, e))
+        });
+        self.register_guarded("writeFile", |caps| caps.filesystem, |path: String, s: String| {
+            fs::write(&path, s).map_err(|e| format!("could not write {}: {}", path, e))
+        });
+        self.register_guarded("appendFile", |caps| caps.filesystem, |path: String, s: String| {
+            fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(s.as_bytes()))
+                .map_err(|e| format!("could not write {}: {}", path, e))
+        });
+        self.register_guarded("listDir", |caps| caps.filesystem, |path: String| {
+            let list = |path: &str| -> io::Result<Vec<String>> {
+                let mut names = fs::read_dir(path)?
+                    .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                    .collect::<io::Result<Vec<_>>>()?;
+                names.sort();
+                Ok(names)
+            };
+            let names = list(&path).map_err(|e| format!("could not list {}: {}", path, e))?;
+            Ok::<_, String>(Value::List(LoxList::new(names.into_iter().map(Value::from).collect())))
+        });
+
+        let exit = RustFn::new("exit", 1, |interp: &mut Interpreter, args: Vec<Value>| {
+            if !interp.capabilities.exit {
+                return Err(disabled("exit"));
+            }
+            match args[0] {
+                Value::Primitive(Primitive::Number(code)) if code.fract() == 0.0 => {
+                    Err(LoxError::Exit(code as i32))
+                },
+                ref code => Err(LoxError::typecast(format!(
+                    "exit() expects an integer for argument 1, got {}",
+                    code
+                ))),
+            }
+        });
+        self.builtins.define_global("exit", Value::RustFn(exit.into()));
+    }
+
+    /// Registers `f` as a native that can only be called while `allowed`
+    /// holds for the interpreter's capabilities.
+    fn register_guarded<F, Args>(&mut self, name: &str, allowed: fn(&Capabilities) -> bool, f: F)
+    where
+        F: NativeFn<Args>,
+    {
+        let native_name: LoxStr = name.into();
+        let native = RustFn::new(name, f.arity(), move |interp: &mut Interpreter, args| {
+            if !allowed(&interp.capabilities) {
+                return Err(disabled(&native_name));
+            }
+            f.call_native(&native_name, args)
+        });
+        self.builtins.define_global(name, Value::RustFn(native.into()));
+    }
+}
+
+/// The error raised by natives the interpreter's capabilities don't allow.
+fn disabled(name: &str) -> LoxError {
+    LoxError::typecast(format!("{}() is disabled", name)).with_kind(ErrorKind::Runtime)
+}
@@ -5,36 +5,135 @@
 mod ast;
 mod callable;
 mod class;
+mod compiler;
+mod diagnostic;
 mod environment;
 mod error;
+mod gc;
 mod instance;
 mod interpreter;
+mod io;
+mod limits;
+mod lint;
+mod list;
+mod map;
+mod math;
+mod module;
+mod native;
+mod native_class;
+mod number;
 mod parser;
 mod print_ast;
 mod resolver;
 mod run;
 mod scanner;
+mod span;
+mod string;
+mod symbol;
 mod token;
+mod trace;
 mod value;
+mod vm;
 
 #[allow(unused_imports)]
 use self::{
     ast::*,
     callable::*,
     class::*,
+    compiler::*,
+    diagnostic::*,
     environment::*,
     error::*,
+    gc::*,
     instance::*,
     interpreter::*,
+    io::*,
+    limits::*,
+    lint::*,
+    list::*,
+    map::*,
+    math::*,
+    module::*,
+    native::*,
+    native_class::*,
+    number::*,
     parser::*,
     print_ast::*,
     resolver::*,
     scanner::*,
+    span::*,
+    string::*,
+    symbol::*,
     token::*,
+    trace::*,
     value::*,
+    vm::*,
 };
 
-pub use crate::run::*;
+pub use crate::{
+    callable::{
+        Callable,
+        LoxFn,
+        RustFn,
+    },
+    class::LoxClass,
+    error::{
+        ErrorKind,
+        Errors,
+        LoxError,
+        ResolveError,
+    },
+    gc::{
+        collect_garbage,
+        gc_stats,
+        set_gc_threshold,
+        GcStats,
+        MemoryUsage,
+    },
+    instance::LoxInstance,
+    interpreter::Interpreter,
+    io::{
+        Capabilities,
+        OutputBuffer,
+    },
+    limits::{
+        InterruptHandle,
+        Limits,
+        DEFAULT_MAX_DEPTH,
+    },
+    lint::{
+        Lint,
+        Lints,
+        Warning,
+    },
+    list::LoxList,
+    map::LoxMap,
+    module::LoxModule,
+    native::{
+        NativeArg,
+        NativeFn,
+        NativeInit,
+        NativeMethod,
+        NativeResult,
+        NativeReturn,
+    },
+    native_class::NativeClass,
+    number::NumberFormat,
+    run::*,
+    span::{
+        Location,
+        Span,
+    },
+    symbol::{
+        Symbol,
+        SymbolMap,
+    },
+    trace::StackTrace,
+    value::{
+        Primitive,
+        Value,
+    },
+};
 
 use tendril::StrTendril;
 
@@ -0,0 +1,232 @@
+use std::{
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::*;
+
+/// Bounds on the work a script may do, so that a runaway one ends with an
+/// error rather than hanging or crashing the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Statements the tree-walker, or instructions the VM, may execute in a
+    /// single run.
+    pub max_steps:  Option<u64>,
+    /// Calls that may be in progress at once. Each tree-walked call uses the
+    /// native stack, so calls also fail once the thread is running out of
+    /// it, whatever this is.
+    pub max_depth:  Option<usize>,
+    /// How long a single run may take.
+    pub timeout:    Option<Duration>,
+    /// Approximately how many bytes the objects, strings and closures
+    /// scripts allocate may take up, as counted by
+    /// `Interpreter::memory_usage`.
+    pub max_memory: Option<usize>,
+}
+
+/// The call depth allowed by default.
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+/// Steps between checks of the deadline and interrupt flag.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Native stack calls leave free, for the deepest a single call can recurse
+/// before it makes the next one, and for reporting the overflow.
+const STACK_RESERVE: usize = 256 << 10;
+
+impl Limits {
+    /// No limits at all, not even on call depth. Calls still fail when the
+    /// native stack runs low.
+    pub fn none() -> Self {
+        Limits {
+            max_steps:  None,
+            max_depth:  None,
+            timeout:    None,
+            max_memory: None,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            ..Limits::none()
+        }
+    }
+}
+
+/// Stops an `Interpreter` from another thread. Scripts check for it as they
+/// run, so natives blocked on input aren't interrupted until they return.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Stops the running script, or the next one to run if none is.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    /// Clears a pending interrupt, returning whether there was one.
+    fn take(&self) -> bool {
+        self.interrupted.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// The interpreter's progress against its `Limits`.
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    steps:      u64,
+    /// The step count at which the limits are next checked.
+    next_check: u64,
+    deadline:   Option<Instant>,
+    /// Runs in progress. The budget starts over with the outermost one.
+    runs:       usize,
+    interrupt:  InterruptHandle,
+}
+
+impl Interpreter {
+    /// A handle for stopping this interpreter's scripts from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.budget.interrupt.clone()
+    }
+
+    /// Runs `f` as a run the limits apply to, starting the step count and
+    /// clock over unless it's nested in another one. Numbers are displayed in
+    /// this interpreter's `number_format` meanwhile, and what's allocated is
+    /// counted as this interpreter's.
+    pub(crate) fn limited<F, T>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut Interpreter) -> T,
+    {
+        if self.budget.runs == 0 {
+            self.budget.steps = 0;
+            self.budget.next_check = 0;
+            self.budget.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+        }
+        self.budget.runs += 1;
+        let (format, owner) = (self.number_format, self.owner);
+        let res = with_number_format(format, || allocating_as(owner, || f(self)));
+        self.budget.runs -= 1;
+        res
+    }
+
+    /// Counts a statement or instruction about to execute at `span` against
+    /// the limits.
+    #[inline]
+    pub(crate) fn tick<F>(&mut self, span: F) -> Result<(), LoxError>
+    where
+        F: FnOnce() -> Span,
+    {
+        self.budget.steps += 1;
+        if self.budget.steps >= self.budget.next_check {
+            self.check_budget(span())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cold]
+    fn check_budget(&mut self, span: Span) -> Result<(), LoxError> {
+        let steps = self.budget.steps;
+        if let Some(max) = self.limits.max_steps {
+            if steps > max {
+                return Err(LoxError::halt(span, format!("step limit of {} exceeded", max)));
+            }
+        }
+        if self.budget.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(LoxError::halt(span, "timed out"));
+        }
+        if self.budget.interrupt.take() {
+            return Err(LoxError::halt(span, "interrupted"));
+        }
+        self.budget.next_check = match self.limits.max_steps {
+            Some(max) => (steps + CHECK_INTERVAL).min(max + 1),
+            None => steps + CHECK_INTERVAL,
+        };
+        Ok(())
+    }
+
+    /// Fails with a stack overflow if another call from `call_site` would go
+    /// deeper than the limit, or the native stack is nearly used up.
+    pub(crate) fn check_depth(&self, call_site: Span) -> Result<(), LoxError> {
+        let too_deep = self.limits.max_depth.is_some_and(|max| self.frames.len() >= max);
+        if too_deep || stack_remaining().is_some_and(|left| left < STACK_RESERVE) {
+            return Err(LoxError::runtime_at(call_site, "stack overflow"));
+        }
+        Ok(())
+    }
+
+    /// Approximately how much memory this interpreter's scripts are using.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        memory_usage(self.owner)
+    }
+
+    /// Fails if scripts are using more memory than the limit, once cycles
+    /// have been collected to be sure of it. Called where collections can
+    /// run.
+    pub(crate) fn check_memory(&self, span: Span) -> Result<(), LoxError> {
+        let max = match self.limits.max_memory {
+            Some(max) if self.memory_usage().total() > max => max,
+            _ => return Ok(()),
+        };
+        collect_garbage();
+        if self.memory_usage().total() > max {
+            return Err(LoxError::runtime_at(
+                span,
+                format!("out of memory: scripts may use at most {} bytes", max),
+            ));
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The lowest address of this thread's stack, if it could be found.
+    static STACK_END: Option<usize> = stack_end();
+}
+
+/// Roughly how many bytes of native stack this thread has left.
+fn stack_remaining() -> Option<usize> {
+    let marker = 0u8;
+    let here = &marker as *const u8 as usize;
+    STACK_END.with(|end| end.map(|end| here.saturating_sub(end)))
+}
+
+#[cfg(target_os = "linux")]
+fn stack_end() -> Option<usize> {
+    // Safe: `attr` is initialized by `pthread_getattr_np` before it's read,
+    // and destroyed once the stack's bounds have been read from it.
+    unsafe {
+        let mut attr: libc::pthread_attr_t = std::mem::zeroed();
+        if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) != 0 {
+            return None;
+        }
+        let mut addr = std::ptr::null_mut();
+        let mut size = 0;
+        let res = libc::pthread_attr_getstack(&attr, &mut addr, &mut size);
+        libc::pthread_attr_destroy(&mut attr);
+        if res == 0 {
+            Some(addr as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// Elsewhere, only `Limits::max_depth` guards the stack.
+#[cfg(not(target_os = "linux"))]
+fn stack_end() -> Option<usize> {
+    None
+}
@@ -0,0 +1,199 @@
+use failure::Fail;
+
+use std::{
+    fmt,
+    io,
+    str::FromStr,
+};
+
+use crate::*;
+
+/// Something the `Resolver` warns about: code that runs, but probably
+/// doesn't do what was meant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A local variable that's never read.
+    UnusedVariable,
+    /// A function parameter that's never read.
+    UnusedParameter,
+    /// A statement that can't run, because control always leaves before it
+    /// through a `return`, `break`, `continue` or `throw`.
+    UnreachableCode,
+    /// A parameter with the same name as a variable the function could
+    /// otherwise see.
+    ShadowedParameter,
+    /// A global initialized with its own value, as in `var a = a;`.
+    SelfReference,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 5] = [
+        Lint::UnusedVariable,
+        Lint::UnusedParameter,
+        Lint::UnreachableCode,
+        Lint::ShadowedParameter,
+        Lint::SelfReference,
+    ];
+
+    /// The name the lint is shown and allowed by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnusedParameter => "unused_parameter",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::ShadowedParameter => "shadowed_parameter",
+            Lint::SelfReference => "self_reference",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.name() == name)
+            .ok_or_else(|| format!("unknown lint: {}", name))
+    }
+}
+
+/// The lints that are checked. Every lint is on by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lints {
+    allowed: u8,
+}
+
+impl Lints {
+    pub fn all() -> Self {
+        Lints {
+            allowed: 0,
+        }
+    }
+
+    pub fn none() -> Self {
+        Lints {
+            allowed: !0,
+        }
+    }
+
+    /// Silences `lint`.
+    pub fn allow(&mut self, lint: Lint) -> &mut Self {
+        self.allowed |= lint.bit();
+        self
+    }
+
+    /// Checks `lint` again after it was allowed.
+    pub fn warn(&mut self, lint: Lint) -> &mut Self {
+        self.allowed &= !lint.bit();
+        self
+    }
+
+    pub fn is_enabled(&self, lint: Lint) -> bool {
+        self.allowed & lint.bit() == 0
+    }
+}
+
+impl Default for Lints {
+    fn default() -> Self {
+        Lints::all()
+    }
+}
+
+/// A lint that found something. Reported like errors, but never stops a
+/// script from running.
+#[derive(Debug, Clone)]
+pub struct Warning {
+    pub lint:     Lint,
+    pub location: Location,
+    pub message:  String,
+    /// Another span the warning refers to, and what's there.
+    pub related:  Option<(Span, String)>,
+    pub help:     Option<String>,
+}
+
+impl Warning {
+    pub fn new<S>(lint: Lint, span: Span, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Warning {
+            lint,
+            location: span.into(),
+            message:  message.into(),
+            related:  None,
+            help:     None,
+        }
+    }
+
+    pub fn with_related<S>(mut self, span: Span, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.related = Some((span, message.into()));
+        self
+    }
+
+    pub fn with_help<S>(mut self, help: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Attributes this warning to `file`, unless it already names one.
+    pub fn in_file<S>(mut self, file: S) -> Self
+    where
+        S: Into<String>,
+    {
+        if self.location.file.is_none() {
+            self.location.file = Some(file.into());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} Warning: {}", self.location, self.message)
+    }
+}
+
+impl Fail for Warning {}
+
+impl ToDiagnostic for Warning {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::warning(self.message.clone())
+            .with_code(self.lint.name())
+            .with_location(self.location.clone());
+        if let Some((span, message)) = &self.related {
+            diagnostic = diagnostic.with_secondary(*span, message.clone());
+        }
+        if let Some(help) = &self.help {
+            diagnostic = diagnostic.with_help(help.clone());
+        }
+        diagnostic
+    }
+}
+
+impl Interpreter {
+    /// Writes `warnings` to the error output, if there are any.
+    pub(crate) fn warn(&mut self, warnings: Reporter<Warning>) -> io::Result<()> {
+        match warnings.finish() {
+            Err(warnings) => self.report(&format!("{}\n", warnings)),
+            Ok(()) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    mem,
+    rc::Rc,
+};
+
+use crate::*;
+
+#[derive(Debug, Clone)]
+pub struct LoxList {
+    inner: ListHandle,
+}
+
+type ListHandle = Rc<RefCell<Vec<Value>>>;
+
+impl PartialEq<LoxList> for LoxList {
+    fn eq(&self, right: &LoxList) -> bool {
+        Rc::ptr_eq(&self.inner, &right.inner)
+    }
+}
+
+impl Trace for Vec<Value> {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self {
+            tracer.value(value);
+        }
+    }
+
+    fn size(&self) -> usize {
+        mem::size_of::<Vec<Value>>()
+            + self.capacity() * mem::size_of::<Value>()
+            + strings_size(self)
+    }
+
+    fn clear(&mut self) {
+        Vec::clear(self);
+    }
+}
+
+impl fmt::Display for LoxList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_once(f, Rc::as_ptr(&self.inner) as usize, "[...]", |f| {
+            write!(f, "[")?;
+            for (idx, value) in RefCell::borrow(&self.inner).iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
+            write!(f, "]")
+        })
+    }
+}
+
+impl LoxList {
+    pub fn new(values: Vec<Value>) -> Self {
+        let inner = Rc::new(RefCell::new(values));
+        track(&inner);
+        LoxList {
+            inner,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.object(&self.inner);
+    }
+
+    pub fn len(&self) -> usize {
+        RefCell::borrow(&self.inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: &Value) -> Result<Value, LoxError> {
+        let index = self.index(index, self.len())?;
+        Ok(RefCell::borrow(&self.inner)[index].clone())
+    }
+
+    pub fn set(&self, index: &Value, value: Value) -> Result<(), LoxError> {
+        let index = self.index(index, self.len())?;
+        self.inner.borrow_mut()[index] = value;
+        Ok(())
+    }
+
+    /// Looks up one of the native list methods, bound to this list.
+    pub fn method(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "push" => self.bind("push", 1, |list, mut args| {
+                list.inner.borrow_mut().push(args.remove(0));
+                allocate(mem::size_of::<Value>());
+                Ok(Primitive::Nil.into())
+            }),
+            "pop" => self.bind("pop", 0, |list, _| {
+                list.inner
+                    .borrow_mut()
+                    .pop()
+                    .ok_or_else(|| {
+                        LoxError::typecast("pop from empty list").with_kind(ErrorKind::Index)
+                    })
+            }),
+            "len" => self.bind("len", 0, |list, _| Ok(Primitive::Number(list.len() as f64).into())),
+            "insert" => self.bind("insert", 2, |list, mut args| {
+                let value = args.pop().unwrap();
+                let index = list.index(&args[0], list.len() + 1)?;
+                list.inner.borrow_mut().insert(index, value);
+                allocate(mem::size_of::<Value>());
+                Ok(Primitive::Nil.into())
+            }),
+            "remove" => self.bind("remove", 1, |list, args| {
+                let index = list.index(&args[0], list.len())?;
+                Ok(list.inner.borrow_mut().remove(index))
+            }),
+            "slice" => self.bind("slice", 2, |list, args| {
+                let start = list.index(&args[0], list.len() + 1)?;
+                let end = list.index(&args[1], list.len() + 1)?;
+                if start > end {
+                    return Err(LoxError::typecast(format!(
+                        "slice start {} is after end {}",
+                        start, end
+                    ))
+                    .with_kind(ErrorKind::Index));
+                }
+                let values = RefCell::borrow(&list.inner)[start..end].to_vec();
+                Ok(Value::List(LoxList::new(values)))
+            }),
+            _ => return None,
+        })
+    }
+
+    fn bind<F>(&self, name: &str, arity: usize, f: F) -> Value
+    where
+        F: Fn(&LoxList, Vec<Value>) -> Result<Value, LoxError> + 'static,
+    {
+        let list = self.clone();
+        Value::RustFn(RustFn::new(name, arity, move |_, args| f(&list, args)).into())
+    }
+
+    /// Checks that `index` is a whole number below `bound`.
+    fn index(&self, index: &Value, bound: usize) -> Result<usize, LoxError> {
+        let index = *index.primitive().and_then(|p| p.number()).map_err(|_| {
+            LoxError::typecast(format!("list index must be a number, got {}", index))
+        })?;
+        if index.fract() != 0.0 {
+            return Err(LoxError::typecast(format!(
+                "list index must be an integer, got {}",
+                index
+            )));
+        }
+        if index < 0.0 || index >= bound as f64 {
+            return Err(LoxError::typecast(format!(
+                "index {} out of range for list of length {}",
+                index,
+                self.len()
+            ))
+            .with_kind(ErrorKind::Index));
+        }
+        Ok(index as usize)
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    mem,
+    rc::Rc,
+};
+
+use crate::*;
+
+/// The hashable form of a primitive used as a map key. Numbers are keyed by
+/// their bits, with `-0` folded into `0` so that keys that compare equal
+/// with `==` find the same entry. NaN isn't equal to anything, itself
+/// included, so it can't be a key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Number(u64),
+    String(LoxStr),
+}
+
+impl MapKey {
+    pub fn new(value: &Value) -> Result<MapKey, LoxError> {
+        Ok(match value {
+            Value::Primitive(Primitive::Nil) => MapKey::Nil,
+            Value::Primitive(Primitive::Bool(b)) => MapKey::Bool(*b),
+            Value::Primitive(Primitive::Number(n)) if n.is_nan() => {
+                return Err(LoxError::typecast("map keys cannot be NaN"))
+            },
+            Value::Primitive(Primitive::Number(n)) => MapKey::Number((n + 0.0).to_bits()),
+            Value::Primitive(Primitive::String(s)) => MapKey::String(s.clone()),
+            _ => {
+                return Err(LoxError::typecast(format!(
+                    "map keys must be strings, numbers, booleans or nil, got {}",
+                    value.type_name()
+                )))
+            },
+        })
+    }
+}
+
+impl From<MapKey> for Value {
+    fn from(key: MapKey) -> Value {
+        match key {
+            MapKey::Nil => Primitive::Nil.into(),
+            MapKey::Bool(b) => Primitive::Bool(b).into(),
+            MapKey::Number(n) => Primitive::Number(f64::from_bits(n)).into(),
+            MapKey::String(s) => Primitive::String(s).into(),
+        }
+    }
+}
+
+/// Entries in insertion order, plus an index from key to position so that
+/// lookups don't scan. Keeping the order makes printing and iteration
+/// deterministic.
+///
+/// Removing an entry leaves a hole rather than shifting the ones after it,
+/// so that it doesn't have to renumber the index. Holes are compacted away
+/// once they make up half the entries, which keeps removal amortized O(1).
+#[derive(Debug, Default)]
+struct Entries {
+    entries: Vec<Option<(MapKey, Value)>>,
+    index:   HashMap<MapKey, usize>,
+}
+
+/// The bytes an entry takes up, in the list of entries and the index.
+const ENTRY_SIZE: usize =
+    mem::size_of::<Option<(MapKey, Value)>>() + mem::size_of::<(MapKey, usize)>();
+
+impl Entries {
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(MapKey, Value)> {
+        self.entries.iter().flatten()
+    }
+
+    fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.index.get(key).and_then(|&idx| self.entries[idx].as_ref()).map(|(_, value)| value)
+    }
+
+    fn insert(&mut self, key: MapKey, value: Value) {
+        match self.index.get(&key) {
+            Some(&idx) => self.entries[idx] = Some((key, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, value)));
+                allocate(ENTRY_SIZE);
+            },
+        }
+    }
+
+    fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let idx = self.index.remove(key)?;
+        let (_, value) = self.entries[idx].take()?;
+        if self.entries.len() >= 2 * self.len() {
+            self.compact();
+        }
+        Some(value)
+    }
+
+    /// Drops the holes left by removed entries.
+    fn compact(&mut self) {
+        self.entries.retain(Option::is_some);
+        for (idx, (key, _)) in self.entries.iter().flatten().enumerate() {
+            *self.index.get_mut(key).unwrap() = idx;
+        }
+    }
+}
+
+impl Trace for Entries {
+    fn trace(&self, tracer: &mut Tracer) {
+        for (_, value) in self.iter() {
+            tracer.value(value);
+        }
+    }
+
+    fn size(&self) -> usize {
+        let keys: usize = self
+            .iter()
+            .map(|(key, _)| match key {
+                MapKey::String(key) => key.len(),
+                _ => 0,
+            })
+            .sum();
+        mem::size_of::<Entries>()
+            + self.entries.capacity() * ENTRY_SIZE
+            + keys
+            + strings_size(self.iter().map(|(_, value)| value))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoxMap {
+    inner: Rc<RefCell<Entries>>,
+}
+
+impl PartialEq<LoxMap> for LoxMap {
+    fn eq(&self, right: &LoxMap) -> bool {
+        Rc::ptr_eq(&self.inner, &right.inner)
+    }
+}
+
+impl fmt::Display for LoxMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        display_once(f, Rc::as_ptr(&self.inner) as usize, "{...}", |f| {
+            write!(f, "{{")?;
+            for (idx, (key, value)) in RefCell::borrow(&self.inner).iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", Value::from(key.clone()), value)?;
+            }
+            write!(f, "}}")
+        })
+    }
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        let inner = Rc::new(RefCell::new(Entries::default()));
+        track(&inner);
+        LoxMap {
+            inner,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.object(&self.inner);
+    }
+
+    pub fn len(&self) -> usize {
+        RefCell::borrow(&self.inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &Value) -> Result<Value, LoxError> {
+        let key = MapKey::new(key)?;
+        RefCell::borrow(&self.inner)
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| {
+                LoxError::typecast(format!("undefined key: {}", Value::from(key)))
+                    .with_kind(ErrorKind::Index)
+            })
+    }
+
+    pub fn set(&self, key: &Value, value: Value) -> Result<(), LoxError> {
+        let key = MapKey::new(key)?;
+        self.inner.borrow_mut().insert(key, value);
+        Ok(())
+    }
+
+    /// Looks up one of the native map methods, bound to this map. `keys`,
+    /// `values` and `entries` return new lists, in insertion order, so the
+    /// map can be changed while they're iterated over.
+    pub fn method(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "keys" => self.bind("keys", 0, |map, _| {
+                let keys = RefCell::borrow(&map.inner)
+                    .iter()
+                    .map(|(key, _)| key.clone().into())
+                    .collect();
+                Ok(Value::List(LoxList::new(keys)))
+            }),
+            "values" => self.bind("values", 0, |map, _| {
+                let values =
+                    RefCell::borrow(&map.inner).iter().map(|(_, v)| v.clone()).collect();
+                Ok(Value::List(LoxList::new(values)))
+            }),
+            "entries" => self.bind("entries", 0, |map, _| {
+                let entries = RefCell::borrow(&map.inner)
+                    .iter()
+                    .map(|(key, value)| {
+                        Value::List(LoxList::new(vec![key.clone().into(), value.clone()]))
+                    })
+                    .collect();
+                Ok(Value::List(LoxList::new(entries)))
+            }),
+            "has" => self.bind("has", 1, |map, args| {
+                let key = MapKey::new(&args[0])?;
+                Ok(Primitive::Bool(RefCell::borrow(&map.inner).get(&key).is_some()).into())
+            }),
+            "remove" => self.bind("remove", 1, |map, args| {
+                let key = MapKey::new(&args[0])?;
+                Ok(map.inner.borrow_mut().remove(&key).unwrap_or_else(|| Primitive::Nil.into()))
+            }),
+            "len" => self.bind("len", 0, |map, _| Ok(Primitive::Number(map.len() as f64).into())),
+            _ => return None,
+        })
+    }
+
+    fn bind<F>(&self, name: &str, arity: usize, f: F) -> Value
+    where
+        F: Fn(&LoxMap, Vec<Value>) -> Result<Value, LoxError> + 'static,
+    {
+        let map = self.clone();
+        Value::RustFn(RustFn::new(name, arity, move |_, args| f(&map, args)).into())
+    }
+}
+
+impl Default for LoxMap {
+    fn default() -> Self {
+        LoxMap::new()
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    cell::Cell,
+    f64::consts::PI,
+    rc::Rc,
+    time,
+};
+
+use crate::*;
+
+/// Builds the `math` module of numeric natives.
+pub fn math_module() -> LoxModule {
+    let math = Environment::new();
+    let define = |name: &str, value: Value| math.define_global(name, value);
+    let unary = |name: &str, f: fn(f64) -> f64| define(name, native(name, f));
+
+    define("pi", Primitive::Number(PI).into());
+    unary("sqrt", f64::sqrt);
+    unary("floor", f64::floor);
+    unary("ceil", f64::ceil);
+    unary("round", f64::round);
+    unary("abs", f64::abs);
+    unary("sin", f64::sin);
+    unary("cos", f64::cos);
+    unary("tan", f64::tan);
+    unary("asin", f64::asin);
+    unary("acos", f64::acos);
+    unary("atan", f64::atan);
+    define("atan2", native("atan2", f64::atan2));
+    define("pow", native("pow", f64::powf));
+    define("min", native("min", f64::min));
+    define("max", native("max", f64::max));
+
+    let rng = Rc::new(SplitMix64::new(default_seed()));
+    let random = rng.clone();
+    define("random", native("random", move || random.next_f64()));
+    define("seed", native("seed", move |seed: f64| rng.seed(seed.to_bits())));
+
+    LoxModule::new("math", math)
+}
+
+/// A small, fast PRNG. Scripts only need reproducible sequences for a given
+/// seed, not cryptographic quality.
+#[derive(Debug)]
+struct SplitMix64 {
+    state: Cell<u64>,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        SplitMix64 {
+            state: Cell::new(seed),
+        }
+    }
+
+    fn seed(&self, seed: u64) {
+        self.state.set(seed);
+    }
+
+    fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`, from the top 53 bits.
+    fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn default_seed() -> u64 {
+    time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Converts a string or number to a number, or `nil` if a string doesn't
+/// parse as one.
+pub fn to_number(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Primitive(Primitive::Number(_)) => Ok(value),
+        Value::Primitive(Primitive::String(s)) => {
+            Ok(s.trim().parse::<f64>().map(Value::from).unwrap_or_else(|_| ().into()))
+        },
+        _ => Err(format!("num() expects a string or number, got {}", value.type_name())),
+    }
+}
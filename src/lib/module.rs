@@ -0,0 +1,121 @@
+use std::{
+    fmt,
+    fs,
+    path::Path,
+};
+
+use crate::*;
+
+/// The top-level bindings of an imported file.
+#[derive(Debug, Clone)]
+pub struct LoxModule {
+    pub name: LoxStr,
+    globals:  Environment,
+}
+
+impl PartialEq<LoxModule> for LoxModule {
+    fn eq(&self, right: &LoxModule) -> bool {
+        self.globals.same_scope(&right.globals)
+    }
+}
+
+impl fmt::Display for LoxModule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+impl LoxModule {
+    pub(crate) fn new<S>(name: S, globals: Environment) -> Self
+    where
+        S: Into<LoxStr>,
+    {
+        LoxModule {
+            name: name.into(),
+            globals,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        self.globals.trace(tracer);
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<Value> {
+        self.globals.get_local(name)
+    }
+}
+
+impl Interpreter {
+    /// Runs `f` with `file` as the file being executed, which `import`
+    /// paths are resolved against.
+    pub fn in_file<F, T>(&mut self, file: Option<&Path>, f: F) -> T
+    where
+        F: FnOnce(&mut Interpreter) -> T,
+    {
+        let file = file.map(|file| fs::canonicalize(file).unwrap_or_else(|_| file.into()));
+        let pushed = file.is_some();
+        self.files.extend(file);
+        let res = f(self);
+        if pushed {
+            self.files.pop();
+        }
+        res
+    }
+
+    /// Loads the module at `path`, relative to the file being executed, or
+    /// returns it from the cache if it's been imported before.
+    pub fn import(&mut self, span: Span, path: &str) -> Result<LoxModule, LoxError> {
+        if !self.capabilities.filesystem {
+            return Err(LoxError::runtime_at(span, "import is disabled"));
+        }
+        let base = self
+            .files
+            .last()
+            .and_then(|file| file.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let file = base.join(path);
+        let canonical = fs::canonicalize(&file).map_err(|e| {
+            LoxError::runtime_at(span, format!("could not import {}: {}", path, e))
+        })?;
+
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(module.clone());
+        }
+        if let Some(start) = self.files.iter().position(|f| *f == canonical) {
+            let cycle: Vec<_> = self.files[start..]
+                .iter()
+                .chain(Some(&canonical))
+                .map(|f| display_name(f))
+                .collect();
+            return Err(LoxError::runtime_at(
+                span,
+                format!("cyclic import: {}", cycle.join(" -> ")),
+            ));
+        }
+
+        let display = file.display().to_string();
+        let source = fs::read_to_string(&canonical).map_err(|e| {
+            LoxError::runtime_at(span, format!("could not import {}: {}", path, e))
+        })?;
+        let (stmts, warnings) = parse(Some(&display), &source, self.lints)
+            .map_err(|errors| LoxError::Import(span.into(), path.into(), errors.into()))?;
+        // Warnings are only advice, so failing to show them isn't an error.
+        let _ = self.warn(warnings);
+
+        let globals = self.builtins.new_module();
+        let backend = self.backend;
+        self.in_file(Some(&canonical), |interp| {
+            interp.with_env(globals.clone(), |interp| execute(false, backend, interp, stmts))
+        })
+        .map_err(|err| err.in_file(display))?;
+
+        let module = LoxModule::new(path, globals);
+        self.modules.insert(canonical, module.clone());
+        Ok(module)
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
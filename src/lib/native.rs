@@ -0,0 +1,272 @@
+use std::fmt::Display;
+
+use crate::*;
+
+/// A type natives can take as an argument, converted from the `Value` the
+/// caller passed.
+pub trait NativeArg: Sized {
+    /// What the argument is expected to be, for type errors.
+    fn describe() -> String;
+
+    fn from_arg(value: Value) -> Option<Self>;
+}
+
+impl NativeArg for Value {
+    fn describe() -> String {
+        "value".into()
+    }
+
+    fn from_arg(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl NativeArg for f64 {
+    fn describe() -> String {
+        "number".into()
+    }
+
+    fn from_arg(value: Value) -> Option<Self> {
+        match value {
+            Value::Primitive(Primitive::Number(n)) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+impl NativeArg for bool {
+    fn describe() -> String {
+        "bool".into()
+    }
+
+    fn from_arg(value: Value) -> Option<Self> {
+        match value {
+            Value::Primitive(Primitive::Bool(b)) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl NativeArg for String {
+    fn describe() -> String {
+        "string".into()
+    }
+
+    fn from_arg(value: Value) -> Option<Self> {
+        match value {
+            Value::Primitive(Primitive::String(s)) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl<T> NativeArg for Option<T>
+where
+    T: NativeArg,
+{
+    fn describe() -> String {
+        format!("{} or nil", T::describe())
+    }
+
+    fn from_arg(value: Value) -> Option<Self> {
+        match value {
+            Value::Primitive(Primitive::Nil) => Some(None),
+            value => T::from_arg(value).map(Some),
+        }
+    }
+}
+
+/// A type natives can return. Returning `Err` raises a runtime error at the
+/// call site with the error's message.
+pub trait NativeReturn {
+    fn into_result(self) -> Result<Value, LoxError>;
+}
+
+impl<T> NativeReturn for T
+where
+    T: Into<Value>,
+{
+    fn into_result(self) -> Result<Value, LoxError> {
+        Ok(self.into())
+    }
+}
+
+impl<T, E> NativeReturn for Result<T, E>
+where
+    T: Into<Value>,
+    E: Display,
+{
+    fn into_result(self) -> Result<Value, LoxError> {
+        self.map(Into::into)
+            .map_err(|e| LoxError::typecast(e.to_string()).with_kind(ErrorKind::Runtime))
+    }
+}
+
+/// A type native constructors can return: either the value itself, or a
+/// `Result` whose `Err` raises a runtime error at the call site.
+pub trait NativeResult<T> {
+    fn into_native(self) -> Result<T, LoxError>;
+}
+
+impl<T> NativeResult<T> for T {
+    fn into_native(self) -> Result<T, LoxError> {
+        Ok(self)
+    }
+}
+
+impl<T, E> NativeResult<T> for Result<T, E>
+where
+    E: Display,
+{
+    fn into_native(self) -> Result<T, LoxError> {
+        self.map_err(|e| LoxError::typecast(e.to_string()).with_kind(ErrorKind::Runtime))
+    }
+}
+
+/// A plain Rust function that can be registered as a native, taking the
+/// tuple of argument types `Args`.
+pub trait NativeFn<Args>: 'static {
+    fn arity(&self) -> usize;
+
+    fn call_native(&self, name: &str, args: Vec<Value>) -> Result<Value, LoxError>;
+}
+
+/// A Rust function taking `&mut T` and then `Args`, used as a method of a
+/// native class wrapping a `T`.
+pub trait NativeMethod<T, Args>: 'static {
+    fn arity(&self) -> usize;
+
+    fn call_method(&self, name: &str, this: &mut T, args: Vec<Value>) -> Result<Value, LoxError>;
+}
+
+/// A Rust function building the `T` wrapped by instances of a native class
+/// from the arguments passed to its initializer.
+pub trait NativeInit<T, Args>: 'static {
+    fn arity(&self) -> usize;
+
+    fn construct(&self, name: &str, args: Vec<Value>) -> Result<T, LoxError>;
+}
+
+fn arg<T>(name: &str, idx: usize, value: Value) -> Result<T, LoxError>
+where
+    T: NativeArg,
+{
+    let type_name = value.type_name();
+    T::from_arg(value).ok_or_else(|| {
+        LoxError::typecast(format!(
+            "{}() expects a {} for argument {}, got {}",
+            name,
+            T::describe(),
+            idx + 1,
+            type_name
+        ))
+    })
+}
+
+/// Binds each of `$arg` to the converted argument of that type. Natives
+/// without arguments have nothing to convert.
+macro_rules! native_args {
+    ($name:expr, $args:expr;) => {
+        let _ = ($name, $args);
+    };
+    ($name:expr, $args:expr; $($arg:ident),+) => {
+        let mut args = $args.into_iter().enumerate();
+        $(
+            let $arg = match args.next() {
+                Some((idx, value)) => arg::<$arg>($name, idx, value)?,
+                None => unreachable!("arity is checked before natives are called"),
+            };
+        )+
+    };
+}
+
+macro_rules! native_fn {
+    ($arity:expr; $($arg:ident),*) => {
+        impl<Func, Ret, $($arg),*> NativeFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + 'static,
+            Ret: NativeReturn,
+            $($arg: NativeArg,)*
+        {
+            fn arity(&self) -> usize {
+                $arity
+            }
+
+            #[allow(non_snake_case)]
+            fn call_native(&self, name: &str, args: Vec<Value>) -> Result<Value, LoxError> {
+                native_args!(name, args; $($arg),*);
+                (self)($($arg),*).into_result()
+            }
+        }
+
+        impl<Func, This, Ret, $($arg),*> NativeMethod<This, ($($arg,)*)> for Func
+        where
+            Func: Fn(&mut This, $($arg),*) -> Ret + 'static,
+            Ret: NativeReturn,
+            $($arg: NativeArg,)*
+        {
+            fn arity(&self) -> usize {
+                $arity
+            }
+
+            #[allow(non_snake_case)]
+            fn call_method(
+                &self,
+                name: &str,
+                this: &mut This,
+                args: Vec<Value>,
+            ) -> Result<Value, LoxError> {
+                native_args!(name, args; $($arg),*);
+                (self)(this, $($arg),*).into_result()
+            }
+        }
+
+        impl<Func, This, Ret, $($arg),*> NativeInit<This, ($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + 'static,
+            Ret: NativeResult<This>,
+            $($arg: NativeArg,)*
+        {
+            fn arity(&self) -> usize {
+                $arity
+            }
+
+            #[allow(non_snake_case)]
+            fn construct(&self, name: &str, args: Vec<Value>) -> Result<This, LoxError> {
+                native_args!(name, args; $($arg),*);
+                (self)($($arg),*).into_native()
+            }
+        }
+    };
+}
+
+native_fn!(0;);
+native_fn!(1; A);
+native_fn!(2; A, B);
+native_fn!(3; A, B, C);
+native_fn!(4; A, B, C, D);
+native_fn!(5; A, B, C, D, E);
+native_fn!(6; A, B, C, D, E, F);
+native_fn!(7; A, B, C, D, E, F, G);
+native_fn!(8; A, B, C, D, E, F, G, H);
+
+impl Interpreter {
+    /// Defines `f` as a native function visible to every module, converting
+    /// its arguments and return value.
+    pub fn register<F, Args>(&mut self, name: &str, f: F)
+    where
+        F: NativeFn<Args>,
+    {
+        self.builtins.define_global(name, native(name, f));
+    }
+}
+
+/// Wraps `f` as a native function value named `name`.
+pub(crate) fn native<F, Args>(name: &str, f: F) -> Value
+where
+    F: NativeFn<Args>,
+{
+    let native_name: LoxStr = name.into();
+    let native = RustFn::new(name, f.arity(), move |_, args| f.call_native(&native_name, args));
+    Value::RustFn(native.into())
+}
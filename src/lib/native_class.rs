@@ -0,0 +1,179 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    fmt,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::*;
+
+type MemberFn = Rc<dyn Fn(&LoxInstance, Vec<Value>) -> Result<Value, LoxError>>;
+type DisplayFn = Box<dyn Fn(&dyn Any, &mut fmt::Formatter) -> Option<fmt::Result>>;
+
+/// A method, initializer or property accessor of a native class, taking the
+/// instance it's called on.
+#[derive(Clone)]
+pub(crate) struct NativeMember {
+    pub name:  LoxStr,
+    pub arity: usize,
+    f:         MemberFn,
+}
+
+impl NativeMember {
+    pub fn call(&self, instance: &LoxInstance, args: Vec<Value>) -> Result<Value, LoxError> {
+        (self.f)(instance, args)
+    }
+
+    /// Binds this member to `instance`, as a function scripts can call.
+    pub fn bind(&self, instance: &LoxInstance) -> Value {
+        let member = self.clone();
+        let instance = instance.clone();
+        Value::RustFn(
+            RustFn::new(self.name.clone(), self.arity, move |_, args| member.call(&instance, args))
+                .into(),
+        )
+    }
+}
+
+/// The type-erased members of a native class, stored on its `LoxClass`.
+#[derive(Default)]
+pub(crate) struct NativeMembers {
+    pub methods: SymbolMap<NativeMember>,
+    pub getters: SymbolMap<NativeMember>,
+    pub setters: SymbolMap<NativeMember>,
+    pub display: Option<DisplayFn>,
+}
+
+impl fmt::Debug for NativeMembers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeMembers")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .field("getters", &self.getters.keys().collect::<Vec<_>>())
+            .field("setters", &self.setters.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Builds a class whose instances wrap a Rust `T`. The value is created by
+/// the class's initializer, so Lox subclasses that define their own `init`
+/// must call `super.init(...)` before using native methods.
+pub struct NativeClass<T> {
+    name:    LoxStr,
+    members: NativeMembers,
+    marker:  PhantomData<T>,
+}
+
+impl<T> NativeClass<T>
+where
+    T: 'static,
+{
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<LoxStr>,
+    {
+        NativeClass {
+            name:    name.into(),
+            members: NativeMembers::default(),
+            marker:  PhantomData,
+        }
+    }
+
+    /// Sets the initializer, which builds the wrapped value from the
+    /// arguments the class is called with.
+    pub fn init<F, Args>(mut self, f: F) -> Self
+    where
+        F: NativeInit<T, Args>,
+    {
+        let arity = f.arity();
+        let member = member("init", arity, move |instance, args| {
+            let data = f.construct("init", args)?;
+            instance.set_native_data(Rc::new(RefCell::new(data)));
+            Ok(Value::Instance(instance.clone()))
+        });
+        self.members.methods.insert(Symbol::INIT, member);
+        self
+    }
+
+    pub fn method<F, Args>(mut self, name: &str, f: F) -> Self
+    where
+        F: NativeMethod<T, Args>,
+    {
+        let member = self.wrap(name, f);
+        self.members.methods.insert(name.into(), member);
+        self
+    }
+
+    /// Adds a property read with `instance.name`.
+    pub fn getter<F>(mut self, name: &str, f: F) -> Self
+    where
+        F: NativeMethod<T, ()>,
+    {
+        let member = self.wrap(name, f);
+        self.members.getters.insert(name.into(), member);
+        self
+    }
+
+    /// Adds a property written with `instance.name = value`. Without a
+    /// setter, assignments create a plain field shadowing the getter.
+    pub fn setter<F, A>(mut self, name: &str, f: F) -> Self
+    where
+        F: NativeMethod<T, (A,)>,
+    {
+        let member = self.wrap(name, f);
+        self.members.setters.insert(name.into(), member);
+        self
+    }
+
+    /// Sets how instances are printed.
+    pub fn display<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T, &mut fmt::Formatter) -> fmt::Result + 'static,
+    {
+        self.members.display =
+            Some(Box::new(move |data, fmt| data.downcast_ref::<T>().map(|data| f(data, fmt))));
+        self
+    }
+
+    fn wrap<F, Args>(&self, name: &str, f: F) -> NativeMember
+    where
+        F: NativeMethod<T, Args>,
+    {
+        let class: LoxStr = self.name.clone();
+        let method: LoxStr = name.into();
+        member(name, f.arity(), move |instance, args| {
+            let data = instance.native_data();
+            let mut data = data.as_ref().map(|data| data.borrow_mut());
+            match data.as_mut().and_then(|data| data.downcast_mut::<T>()) {
+                Some(this) => f.call_method(&method, this, args),
+                None => Err(LoxError::typecast(format!(
+                    "{}() called on an instance of {} whose {} initializer has not run",
+                    method,
+                    instance.class_name(),
+                    class
+                ))),
+            }
+        })
+    }
+}
+
+fn member<F>(name: &str, arity: usize, f: F) -> NativeMember
+where
+    F: Fn(&LoxInstance, Vec<Value>) -> Result<Value, LoxError> + 'static,
+{
+    NativeMember {
+        name: name.into(),
+        arity,
+        f: Rc::new(f),
+    }
+}
+
+impl Interpreter {
+    /// Defines a native class visible to every module, which scripts can
+    /// instantiate, subclass and print like any other class.
+    pub fn register_class<T>(&mut self, class: NativeClass<T>) -> Rc<LoxClass> {
+        let class = Rc::new(LoxClass::native(class.name, class.members));
+        self.builtins.define_global(class.name.clone(), Value::Class(class.clone()));
+        class
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    cell::Cell,
+    fmt,
+};
+
+/// How numbers are printed, by `print`, `str()` and string concatenation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NumberFormat {
+    /// Rust's shortest round-trip formatting, e.g. `1000000000000000000000`.
+    #[default]
+    Shortest,
+    /// The reference Lox implementation's: Java's `Double.toString` without
+    /// the trailing `.0` of whole numbers, e.g. `1.0E21` and `0.5`.
+    Reference,
+    /// A fixed number of digits after the decimal point.
+    Fixed(usize),
+}
+
+thread_local! {
+    /// The format of the interpreter running on this thread, since `Display`
+    /// has no access to it.
+    static FORMAT: Cell<NumberFormat> = const { Cell::new(NumberFormat::Shortest) };
+}
+
+/// Runs `f` with numbers displayed in `format`, as they are while an
+/// interpreter with that format runs.
+pub(crate) fn with_number_format<F, T>(format: NumberFormat, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let outer = FORMAT.with(|current| current.replace(format));
+    let res = f();
+    FORMAT.with(|current| current.set(outer));
+    res
+}
+
+pub fn format_number(f: &mut fmt::Formatter, n: f64) -> fmt::Result {
+    match FORMAT.with(Cell::get) {
+        NumberFormat::Shortest => write!(f, "{}", n),
+        NumberFormat::Fixed(digits) => write!(f, "{:.*}", digits, n),
+        NumberFormat::Reference => format_reference(f, n),
+    }
+}
+
+fn format_reference(f: &mut fmt::Formatter, n: f64) -> fmt::Result {
+    if n.is_nan() {
+        return write!(f, "NaN");
+    }
+    if n.is_infinite() {
+        return write!(f, "{}Infinity", if n < 0.0 { "-" } else { "" });
+    }
+    let magnitude = n.abs();
+    if magnitude == 0.0 || (1e-3..1e7).contains(&magnitude) {
+        return write!(f, "{}", n);
+    }
+    // Java always has a fractional digit in the mantissa and an upper-case
+    // exponent marker.
+    let scientific = format!("{:e}", n);
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap_or(0));
+    let point = if mantissa.contains('.') { "" } else { ".0" };
+    write!(f, "{}{}E{}", mantissa, point, &exponent[1..])
+}
@@ -1,6 +1,9 @@
 use crate::*;
 
-use std::rc::Rc;
+use std::{
+    collections::VecDeque,
+    rc::Rc,
+};
 
 pub struct Parser<S>
 where
@@ -9,6 +12,11 @@ where
     next:    Option<Token>,
     prev:    Option<Token>,
     scanner: S,
+    /// Results to hand out before parsing any further: errors recovered from
+    /// inside a block, followed by the declaration containing it.
+    pending: VecDeque<Result<Stmt, LoxError>>,
+    /// How many blocks deep the parser is.
+    blocks:  usize,
 }
 
 impl<S> Parser<S>
@@ -21,15 +29,21 @@ where
             next,
             prev: None,
             scanner,
+            pending: VecDeque::new(),
+            blocks: 0,
         }
     }
 
     pub fn parse(&mut self) -> Option<Result<Stmt, LoxError>> {
-        if self.is_at_end() {
-            return None;
+        if self.pending.is_empty() {
+            if self.is_at_end() {
+                return None;
+            }
+            let res = self.declaration();
+            self.pending.push_back(res);
         }
 
-        Some(self.declaration())
+        self.pending.pop_front()
     }
 
     fn declaration(&mut self) -> Result<Stmt, LoxError> {
@@ -39,6 +53,8 @@ where
             self.class_declaration()
         } else if self.is_match(&[TokenType::Var]) {
             self.var_declaration()
+        } else if self.is_match(&[TokenType::Import]) {
+            self.import_declaration()
         } else {
             self.statement()
         };
@@ -105,7 +121,7 @@ where
         let init = if self.is_match(&[TokenType::Equal]) {
             self.expression()?
         } else {
-            Expr::Literal(Primitive::Nil)
+            Expr::Literal(Primitive::Nil, name.span)
         };
 
         self.consume(TokenType::Semicolon, "expect ';' after variable declaration")?;
@@ -113,6 +129,15 @@ where
         Ok(Stmt::Var(name, init))
     }
 
+    fn import_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        let path = self.consume(TokenType::String, "expect module path after 'import'")?.clone();
+        self.consume(TokenType::As, "expect 'as' after module path")?;
+        let name = self.consume(TokenType::Identifier, "expect module name after 'as'")?.clone();
+        self.consume(TokenType::Semicolon, "expect ';' after import")?;
+        Ok(Stmt::Import(keyword, path, name))
+    }
+
     fn statement(&mut self) -> Result<Stmt, LoxError> {
         if self.is_match(&[TokenType::If]) {
             self.if_statement()
@@ -122,10 +147,18 @@ where
             self.print_statement()
         } else if self.is_match(&[TokenType::Return]) {
             self.return_statement()
+        } else if self.is_match(&[TokenType::Break, TokenType::Continue]) {
+            self.jump_statement()
+        } else if self.is_match(&[TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.is_match(&[TokenType::Try]) {
+            self.try_statement()
         } else if self.is_match(&[TokenType::While]) {
             self.while_statement()
         } else if self.is_match(&[TokenType::LeftBrace]) {
-            Ok(Stmt::Block(self.block()?))
+            let start = self.previous().span;
+            let stmts = self.block()?;
+            Ok(Stmt::Block(stmts, self.span_from(start)))
         } else {
             self.expression_statement()
         }
@@ -147,7 +180,57 @@ where
         Ok(Stmt::Return(keyword, expr))
     }
 
+    fn jump_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::Semicolon, format!("expect ';' after '{}'", keyword.lexeme))?;
+        Ok(if keyword.ty == TokenType::Break {
+            Stmt::Break(keyword)
+        } else {
+            Stmt::Continue(keyword)
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "expect ';' after thrown value")?;
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftBrace, "expect '{' after 'try'")?;
+        let body = self.block()?;
+
+        let catch = if self.is_match(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "expect '(' after 'catch'")?;
+            let name = self.consume(TokenType::Identifier, "expect error variable name")?.clone();
+            self.consume(TokenType::RightParen, "expect ')' after error variable")?;
+            self.consume(TokenType::LeftBrace, "expect '{' before catch body")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if self.is_match(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "expect '{' after 'finally'")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(LoxError::parse(
+                self.peek(),
+                "expect 'catch' or 'finally' after try block",
+            ));
+        }
+
+        Ok(Stmt::Try(keyword, body, catch, finally))
+    }
+
     fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "expect '(' after 'for'")?;
         let decl = if self.is_match(&[TokenType::Semicolon]) {
             None
@@ -173,34 +256,30 @@ where
 
         self.consume(TokenType::RightParen, "expect ')' after where clauses")?;
 
-        let mut body = self.statement()?;
-
-        if let Some(inc) = inc {
-            body = Stmt::Block(vec![body, Stmt::Expr(inc)]);
-        }
+        let body = self.statement()?;
+        let span = self.span_from(start);
 
-        if let Some(cond) = cond {
-            body = Stmt::While(cond, body.into());
-        } else {
-            body = Stmt::While(Expr::Literal(Primitive::Bool(true)), body.into());
-        }
+        let cond = cond.unwrap_or_else(|| Expr::Literal(Primitive::Bool(true), start));
+        let mut body = Stmt::While(cond, body.into(), inc, span);
 
         if let Some(decl) = decl {
-            body = Stmt::Block(vec![decl, body]);
+            body = Stmt::Block(vec![decl, body], span);
         }
 
         Ok(body)
     }
 
     fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "expect '(' after 'while'")?;
         let cond = self.expression()?;
         self.consume(TokenType::RightParen, "expect ')' after while condition")?;
         let body = self.statement()?;
-        Ok(Stmt::While(cond, body.into()))
+        Ok(Stmt::While(cond, body.into(), None, self.span_from(start)))
     }
 
     fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "expect '(' after 'if'.")?;
         let cond = self.expression()?;
         self.consume(TokenType::RightParen, "expect '(' after 'if'.")?;
@@ -213,15 +292,22 @@ where
             None
         };
 
-        Ok(Stmt::If(cond, then.into(), otherwise.map(From::from)))
+        Ok(Stmt::If(cond, then.into(), otherwise.map(From::from), self.span_from(start)))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut stmts = vec![];
 
+        self.blocks += 1;
         while !self.check(&[TokenType::RightBrace]) && !self.is_at_end() {
-            stmts.push(self.declaration()?);
+            // Keep going after an error, so that later ones in the block are
+            // reported too.
+            match self.declaration() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(err) => self.pending.push_back(Err(err)),
+            }
         }
+        self.blocks -= 1;
 
         self.consume(TokenType::RightBrace, "expect '}' after block.")?;
 
@@ -229,9 +315,10 @@ where
     }
 
     fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value")?;
-        Ok(Stmt::Print(value))
+        Ok(Stmt::Print(value, self.span_from(start)))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
@@ -252,8 +339,11 @@ where
             let value = self.assignment()?;
 
             return match expr {
-                Expr::Variable(name, depth) => Ok(Expr::Assign(name, value.into(), depth)),
+                Expr::Variable(name, slot) => Ok(Expr::Assign(name, value.into(), slot)),
                 Expr::Get(object, name) => Ok(Expr::Set(object, name, value.into())),
+                Expr::Index(object, bracket, index) => {
+                    Ok(Expr::SetIndex(object, bracket, index, value.into()))
+                },
                 _ => Err(LoxError::parse(&equals, "Invalid assignment target.")),
             };
         }
@@ -361,6 +451,10 @@ where
             } else if self.is_match(&[TokenType::Dot]) {
                 let name = self.consume(TokenType::Identifier, "expect property name after '.'")?;
                 expr = Expr::Get(expr.into(), name.clone());
+            } else if self.is_match(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "expect ']' after index")?;
+                expr = Expr::Index(expr.into(), bracket.clone(), index.into());
             } else {
                 break;
             }
@@ -386,16 +480,16 @@ where
 
         let paren = self.consume(TokenType::RightParen, "expect ')' after arguments")?;
 
-        Ok(Expr::Call(callee.into(), paren.clone(), args))
+        Ok(Expr::Call(callee.into(), paren.clone(), args, MethodCache::default()))
     }
 
     fn primary(&mut self) -> Result<Expr, LoxError> {
         Ok(if self.is_match(&[TokenType::False]) {
-            Expr::Literal(Primitive::Bool(false))
+            Expr::Literal(Primitive::Bool(false), self.previous().span)
         } else if self.is_match(&[TokenType::True]) {
-            Expr::Literal(Primitive::Bool(true))
+            Expr::Literal(Primitive::Bool(true), self.previous().span)
         } else if self.is_match(&[TokenType::Nil]) {
-            Expr::Literal(Primitive::Nil)
+            Expr::Literal(Primitive::Nil, self.previous().span)
         } else if self.is_match(&[TokenType::Super]) {
             let keyword = self.previous().clone();
             self.consume(TokenType::Dot, "expect '.' after 'super'")?;
@@ -404,13 +498,42 @@ where
         } else if self.is_match(&[TokenType::This]) {
             Expr::This(self.previous().clone(), None)
         } else if self.is_match(&[TokenType::Number, TokenType::String]) {
-            Expr::Literal(self.previous().literal.clone())
+            Expr::Literal(self.previous().literal.clone(), self.previous().span)
         } else if self.is_match(&[TokenType::LeftParen]) {
+            let start = self.previous().span;
             let expr = self.expression()?;
             self.consume(TokenType::RightParen, "expect ) after expression.")?;
-            Expr::Grouping(expr.into())
+            Expr::Grouping(expr.into(), self.span_from(start))
         } else if self.is_match(&[TokenType::Identifier]) {
             Expr::Variable(self.previous().clone(), None)
+        } else if self.is_match(&[TokenType::LeftBracket]) {
+            let start = self.previous().span;
+            let mut elements = vec![];
+            if !self.check(&[TokenType::RightBracket]) {
+                loop {
+                    elements.push(self.expression()?);
+                    if !self.is_match(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightBracket, "expect ']' after list elements")?;
+            Expr::List(elements, self.span_from(start))
+        } else if self.is_match(&[TokenType::LeftBrace]) {
+            let start = self.previous().span;
+            let mut entries = vec![];
+            if !self.check(&[TokenType::RightBrace]) {
+                loop {
+                    let key = self.expression()?;
+                    self.consume(TokenType::Colon, "expect ':' after map key")?;
+                    entries.push((key, self.expression()?));
+                    if !self.is_match(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(TokenType::RightBrace, "expect '}' after map entries")?;
+            Expr::Map(entries, self.span_from(start))
         } else {
             return Err(LoxError::parse(self.peek(), "expect expression"));
         })
@@ -427,12 +550,13 @@ where
         return Err(LoxError::parse(self.peek(), msg));
     }
 
-    #[allow(dead_code)]
     fn synchronize(&mut self) {
-        self.advance();
+        if !self.at_block_end() {
+            self.advance();
+        }
 
         while !self.is_at_end() {
-            if self.previous().ty == TokenType::Semicolon {
+            if self.previous().ty == TokenType::Semicolon || self.at_block_end() {
                 return;
             }
 
@@ -440,6 +564,7 @@ where
                 TokenType::Class
                 | TokenType::Fun
                 | TokenType::Var
+                | TokenType::Import
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
@@ -454,6 +579,12 @@ where
         }
     }
 
+    /// Whether the next token closes the block being parsed, which must be
+    /// left for the block to consume.
+    fn at_block_end(&self) -> bool {
+        self.blocks > 0 && self.check(&[TokenType::RightBrace])
+    }
+
     fn is_match(&mut self, types: &[TokenType]) -> bool {
         if self.check(types) {
             self.advance();
@@ -491,6 +622,11 @@ where
     fn previous(&self) -> &Token {
         self.prev.as_ref().unwrap()
     }
+
+    /// The span from `start` through the most recently consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
 }
 
 impl<S> Iterator for Parser<S>
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    mem,
     rc::Rc,
 };
 
@@ -20,43 +20,127 @@ enum ClassType {
     Subclass,
 }
 
+/// A variable declared in one of the scopes being resolved.
+#[derive(Debug)]
+struct Local {
+    slot:    usize,
+    /// Whether the variable's initializer has been resolved.
+    defined: bool,
+    /// Whether the variable is ever read.
+    read:    bool,
+    /// The declaration, which variables bound by the interpreter don't have.
+    name:    Option<Token>,
+    /// What to warn about if the variable is never read.
+    unused:  Lint,
+}
+
+/// Resolves variables to slots and checks that statements are used where
+/// they make sense. Errors are collected rather than returned, so that every
+/// one in a file can be reported at once.
 #[derive(Debug)]
 pub struct Resolver {
-    scopes:   Vec<HashMap<LoxStr, bool>>,
+    scopes:   Vec<SymbolMap<Local>>,
     function: FunctionType,
     class:    ClassType,
+    in_loop:  bool,
+    reporter: Reporter<LoxError>,
+    lints:    Lints,
+    warnings: Reporter<Warning>,
 }
 
 impl Resolver {
     pub fn new() -> Self {
+        Resolver::with_lints(Lints::default())
+    }
+
+    /// A resolver that only checks the lints in `lints`.
+    pub fn with_lints(lints: Lints) -> Self {
         Resolver {
             scopes:   vec![Default::default()],
             function: FunctionType::None,
             class:    ClassType::None,
+            in_loop:  false,
+            reporter: Reporter::new(),
+            lints,
+            warnings: Reporter::new(),
         }
     }
 
-    pub fn analyze(&mut self, mut stmt: Stmt) -> Result<Stmt, LoxError> {
-        self.resolve(&mut stmt)?;
-        Ok(stmt)
+    /// Resolves `stmt`, recording any errors in it for `into_reporter`.
+    pub fn analyze(&mut self, mut stmt: Stmt) -> Stmt {
+        self.resolve(&mut stmt);
+        stmt
+    }
+
+    /// The errors found in every statement analyzed so far.
+    pub fn into_reporter(self) -> Reporter<LoxError> {
+        self.reporter
+    }
+
+    /// Takes the warnings given so far, in the order they appear in the
+    /// source.
+    pub fn take_warnings(&mut self) -> Reporter<Warning> {
+        self.warnings.sort();
+        mem::take(&mut self.warnings)
     }
 
-    fn resolve_all(&mut self, stmts: &mut [Stmt]) -> Result<(), LoxError> {
+    fn error(&mut self, token: &Token, kind: ResolveError) {
+        self.reporter.report(LoxError::resolve(token, kind));
+    }
+
+    fn warn(&mut self, warning: Warning) {
+        if self.lints.is_enabled(warning.lint) {
+            self.warnings.report(warning);
+        }
+    }
+
+    fn resolve_all(&mut self, stmts: &mut [Stmt]) {
+        // Where control last left the block, if it has.
+        let mut exit = None;
+        let mut warned = false;
         for stmt in stmts {
-            self.resolve(stmt)?;
+            if let (Some(exit), false) = (exit, warned) {
+                self.warn(
+                    Warning::new(Lint::UnreachableCode, stmt.span(), "unreachable statement")
+                        .with_related(exit, "any code following this is unreachable"),
+                );
+                warned = true;
+            }
+            self.resolve(stmt);
+            if exit.is_none() {
+                exit = exit_of(stmt);
+            }
         }
-        Ok(())
     }
 
-    fn resolve(&mut self, stmt: &mut Stmt) -> Result<(), LoxError> {
+    fn resolve(&mut self, stmt: &mut Stmt) {
         self.visit(stmt)
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new())
+        self.scopes.push(SymbolMap::default())
     }
     fn end_scope(&mut self) {
-        self.scopes.pop();
+        if let Some(scope) = self.scopes.pop() {
+            for local in scope.values().filter(|local| !local.read) {
+                if let Some(name) = &local.name {
+                    self.warn_unused(name, local.unused);
+                }
+            }
+        }
+    }
+
+    fn warn_unused(&mut self, name: &Token, lint: Lint) {
+        if name.lexeme.starts_with('_') {
+            return;
+        }
+        let what = match lint {
+            Lint::UnusedParameter => "parameter",
+            _ => "variable",
+        };
+        let message = format!("unused {} `{}`", what, name.lexeme);
+        let help = format!("prefix it with an underscore if that's intended: `_{}`", name.lexeme);
+        self.warn(Warning::new(lint, name.span, message).with_help(help));
     }
 
     fn with_scope<F, T>(&mut self, f: F) -> T
@@ -68,37 +152,93 @@ impl Resolver {
         self.end_scope();
         res
     }
-    fn resolve_expr(&mut self, expr: &mut Expr) -> Result<(), LoxError> {
+    fn resolve_expr(&mut self, expr: &mut Expr) {
         self.visit(expr)
     }
-    fn declare(&mut self, name: &Token) -> Result<(), LoxError> {
-        self.scopes
-            .last_mut()
-            .map(|scope| {
-                if scope.contains_key(&name.lexeme) {
-                    return Err(LoxError::parse(
-                        name,
-                        "variable with this name already declared in this scope",
-                    ));
-                }
-                scope.insert(name.lexeme.clone().into(), false);
-                Ok(())
-            })
-            .transpose()
-            .map(|_| ())
+    fn declare(&mut self, name: &Token) {
+        self.declare_as(name, Lint::UnusedVariable);
+    }
+
+    fn declare_param(&mut self, param: &Token) {
+        let shadowed = self
+            .scopes
+            .iter()
+            .rev()
+            .skip(1)
+            .find_map(|scope| scope.get(&param.symbol))
+            .and_then(|local| local.name.as_ref())
+            .map(|name| name.span);
+        if let Some(shadowed) = shadowed {
+            self.warn(
+                Warning::new(
+                    Lint::ShadowedParameter,
+                    param.span,
+                    format!("parameter `{}` shadows an outer variable", param.lexeme),
+                )
+                .with_related(shadowed, "the outer variable is declared here"),
+            );
+        }
+        self.declare_as(param, Lint::UnusedParameter);
+    }
+
+    fn declare_as(&mut self, name: &Token, unused: Lint) {
+        let scope = match self.scopes.last_mut() {
+            Some(scope) => scope,
+            None => return,
+        };
+        if scope.contains_key(&name.symbol) {
+            self.error(name, ResolveError::DuplicateDeclaration);
+            return;
+        }
+        let slot = scope.len();
+        scope.insert(name.symbol, Local {
+            slot,
+            defined: false,
+            read:    false,
+            name:    Some(name.clone()),
+            unused,
+        });
     }
 
     fn define(&mut self, name: &Token) {
         self.scopes
             .last_mut()
-            .and_then(|scope| scope.get_mut(&name.lexeme))
-            .map(|entry| *entry = true);
+            .and_then(|scope| scope.get_mut(&name.symbol))
+            .map(|local| local.defined = true);
+    }
+
+    /// Declares `name` in the innermost scope as already defined, for the
+    /// variables bound by the interpreter rather than by a declaration.
+    fn declare_implicit(&mut self, name: Symbol) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.insert(name, Local {
+            slot:    scope.len(),
+            defined: true,
+            read:    false,
+            name:    None,
+            unused:  Lint::UnusedVariable,
+        });
+    }
+
+    /// Marks the innermost variable called `name` as read.
+    fn read(&mut self, name: &Token) {
+        if let Some(local) =
+            self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(&name.symbol))
+        {
+            local.read = true;
+        }
     }
 
-    fn resolve_local(&mut self, name: &Token, depth: &mut Option<usize>) {
-        for (i, scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                *depth = Some(i);
+    /// Finds the slot of the innermost local called `name`. Variables in the
+    /// outermost scope are globals, which are looked up by name instead.
+    fn resolve_local(&mut self, name: &Token, slot: &mut Option<Slot>) {
+        for (depth, scope) in self.scopes.iter().skip(1).rev().enumerate() {
+            if let Some(local) = scope.get(&name.symbol) {
+                *slot = Some(Slot {
+                    depth,
+                    index: local.slot,
+                });
+                return;
             }
         }
     }
@@ -125,97 +265,180 @@ impl Resolver {
         res
     }
 
+    fn with_loop<F, T>(&mut self, in_loop: bool, f: F) -> T
+    where
+        F: FnOnce(&mut Resolver) -> T,
+    {
+        let enclosing = self.in_loop;
+        self.in_loop = in_loop;
+        let res = f(self);
+        self.in_loop = enclosing;
+        res
+    }
+
     fn resolve_fn(
         &mut self,
         params: &[Token],
         body: &mut [Stmt],
         function: FunctionType,
-    ) -> Result<(), LoxError> {
+    ) {
+        // Loops don't extend into the functions declared inside them.
         self.with_fn(function, |resolver| {
-            resolver.with_scope(|resolver| {
-                for param in params {
-                    resolver.declare(param)?;
-                    resolver.define(param);
-                }
+            resolver.with_loop(false, |resolver| {
+                resolver.with_scope(|resolver| {
+                    // Methods are called with `this` bound ahead of their
+                    // parameters.
+                    if let FunctionType::Method | FunctionType::Initializer = function {
+                        resolver.declare_implicit(Symbol::THIS);
+                    }
+                    for param in params {
+                        resolver.declare_param(param);
+                        resolver.define(param);
+                    }
 
-                resolver.resolve_all(body)?;
-                Ok(())
+                    resolver.resolve_all(body);
+                })
             })
         })
     }
 }
 
+/// Where control leaves `stmt` on every path through it, if it does: the
+/// keyword it leaves through, or the whole statement when it leaves through
+/// each of its branches.
+fn exit_of(stmt: &Stmt) -> Option<Span> {
+    match stmt {
+        Stmt::Break(kw) | Stmt::Continue(kw) => Some(kw.span),
+        Stmt::Return(kw, _) | Stmt::Throw(kw, _) => Some(kw.span),
+        Stmt::Block(stmts, _) => stmts.iter().find_map(exit_of),
+        Stmt::If(_, then, Some(otherwise), span) => {
+            exit_of(then).and(exit_of(otherwise)).map(|_| *span)
+        },
+        Stmt::Try(kw, body, catch, finally) => {
+            let leaves = |stmts: &[Stmt]| stmts.iter().any(|stmt| exit_of(stmt).is_some());
+            let catch_leaves = match catch {
+                Some((_, catch)) => leaves(catch),
+                None => true,
+            };
+            match finally {
+                Some(finally) if leaves(finally) => Some(kw.span),
+                _ if leaves(body) && catch_leaves => Some(kw.span),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
 impl<'a> Visitor<&'a mut Expr> for Resolver {
-    type Output = Result<(), LoxError>;
+    type Output = ();
 
     fn visit(&mut self, expr: &'a mut Expr) -> Self::Output {
         match expr {
-            Expr::Assign(name, init, depth) => {
+            Expr::Assign(name, init, slot) => {
                 let init = Rc::make_mut(init);
-                self.resolve_expr(init)?;
-                self.resolve_local(name, depth);
+                self.resolve_expr(init);
+                self.resolve_local(name, slot);
             },
             Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
-                self.resolve_expr(Rc::make_mut(left))?;
-                self.resolve_expr(Rc::make_mut(right))?;
+                self.resolve_expr(Rc::make_mut(left));
+                self.resolve_expr(Rc::make_mut(right));
             },
-            Expr::Call(callee, _, args) => {
-                self.resolve_expr(Rc::make_mut(callee))?;
+            Expr::Call(callee, _, args, _) => {
+                self.resolve_expr(Rc::make_mut(callee));
                 for arg in args {
-                    self.resolve_expr(arg)?;
+                    self.resolve_expr(arg);
                 }
             },
             Expr::Get(expr, _) => {
-                self.resolve_expr(Rc::make_mut(expr))?;
+                self.resolve_expr(Rc::make_mut(expr));
             },
-            Expr::Grouping(expr) | Expr::Unary(_, expr) => {
-                self.resolve_expr(Rc::make_mut(expr))?;
+            Expr::Index(object, _, index) => {
+                self.resolve_expr(Rc::make_mut(object));
+                self.resolve_expr(Rc::make_mut(index));
+            },
+            Expr::List(elements, _) => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            },
+            Expr::Grouping(expr, _) | Expr::Unary(_, expr) => {
+                self.resolve_expr(Rc::make_mut(expr));
+            },
+            Expr::Literal(..) => {},
+            Expr::Map(entries, _) => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
             },
-            Expr::Literal(_) => {},
             Expr::Set(object, _, value) => {
-                self.visit(Rc::make_mut(object))?;
-                self.visit(Rc::make_mut(value))?;
+                self.visit(Rc::make_mut(object));
+                self.visit(Rc::make_mut(value));
+            },
+            Expr::SetIndex(object, _, index, value) => {
+                self.visit(Rc::make_mut(object));
+                self.visit(Rc::make_mut(index));
+                self.visit(Rc::make_mut(value));
             },
             Expr::Super(tok, _, _) if self.class == ClassType::Class => {
-                return Err(LoxError::parse(tok, "super used in a class with no superclass"))
+                self.error(tok, ResolveError::SuperWithoutSuperclass)
             },
             Expr::Super(tok, _, _) if self.class == ClassType::None => {
-                return Err(LoxError::parse(tok, "super used outside of a class"))
+                self.error(tok, ResolveError::SuperOutsideClass)
             },
-            Expr::Super(tok, _, depth) => self.resolve_local(tok, depth),
+            Expr::Super(tok, _, slot) => self.resolve_local(tok, slot),
             Expr::This(tok, _) if ClassType::None == self.class => {
-                return Err(LoxError::parse(tok, "cannot use 'this' outside of a class"))
+                self.error(tok, ResolveError::ThisOutsideClass)
             },
-            Expr::This(tok, depth) => self.resolve_local(tok, depth),
-            Expr::Variable(name, depth) => {
-                if !self.scopes.is_empty() && !self
+            Expr::This(tok, slot) => self.resolve_local(tok, slot),
+            Expr::Variable(name, slot) => {
+                let declaration = self
                     .scopes
                     .last()
-                    .and_then(|scope| scope.get(&name.lexeme))
-                    .map(|b| *b)
-                    .unwrap_or(true)
-                {
-                    return Err(LoxError::parse(
-                        &name,
-                        "Cannot read local variable in its own initializer",
-                    ));
+                    .and_then(|scope| scope.get(&name.symbol))
+                    .filter(|local| !local.defined)
+                    .and_then(|local| local.name.as_ref())
+                    .map(|name| name.span);
+                match declaration {
+                    Some(_) if self.scopes.len() > 1 => {
+                        self.error(name, ResolveError::OwnInitializer)
+                    },
+                    // A global's initializer can read its previous value.
+                    Some(declaration) => self.warn(
+                        Warning::new(
+                            Lint::SelfReference,
+                            name.span,
+                            format!("`{}` is read in its own initializer", name.lexeme),
+                        )
+                        .with_related(declaration, "declared here")
+                        .with_help("this reads the global's previous value, if it has one"),
+                    ),
+                    None => {},
                 }
 
-                self.resolve_local(&name, depth);
+                self.read(name);
+                self.resolve_local(name, slot);
             },
         }
-        Ok(())
     }
 }
 
 impl<'a> Visitor<&'a mut Stmt> for Resolver {
-    type Output = Result<(), LoxError>;
+    type Output = ();
 
     fn visit(&mut self, stmt: &'a mut Stmt) -> Self::Output {
         match stmt {
-            Stmt::Block(ref mut stmts) => {
-                self.with_scope(|resolver| resolver.resolve_all(stmts))?;
+            Stmt::Block(ref mut stmts, _) => {
+                self.with_scope(|resolver| resolver.resolve_all(stmts));
             },
+            Stmt::Break(kw) if !self.in_loop => {
+                self.error(kw, ResolveError::BreakOutsideLoop);
+            },
+            Stmt::Continue(kw) if !self.in_loop => {
+                self.error(kw, ResolveError::ContinueOutsideLoop);
+            },
+            Stmt::Break(_) | Stmt::Continue(_) => {},
             Stmt::Class(name, superclass, methods) => {
                 let class_type = if superclass.is_some() {
                     ClassType::Subclass
@@ -223,81 +446,103 @@ impl<'a> Visitor<&'a mut Stmt> for Resolver {
                     ClassType::Class
                 };
                 self.with_class(class_type, |resolver| {
-                    resolver.declare(name)?;
+                    resolver.declare(name);
 
-                    if let Some(superclass) = superclass {
-                        resolver.resolve_expr(superclass)?;
+                    match superclass {
+                        Some(Expr::Variable(superclass, _)) if superclass.symbol == name.symbol => {
+                            resolver.error(superclass, ResolveError::InheritFromSelf)
+                        },
+                        Some(superclass) => resolver.resolve_expr(superclass),
+                        None => {},
                     }
 
                     resolver.define(name);
 
                     let resolve_class = |resolver: &mut Resolver| {
-                        resolver.with_scope(|resolver| {
-                            resolver.scopes.last_mut().unwrap().insert("this".into(), true);
-                            for method in methods {
-                                if let Stmt::Function(name, params, body) = method {
-                                    let decl = if &*name.lexeme == "init" {
-                                        FunctionType::Initializer
-                                    } else {
-                                        FunctionType::Method
-                                    };
-                                    resolver.resolve_fn(params, body, decl)?;
-                                }
+                        for method in methods {
+                            if let Stmt::Function(name, params, body) = method {
+                                let decl = if &*name.lexeme == "init" {
+                                    FunctionType::Initializer
+                                } else {
+                                    FunctionType::Method
+                                };
+                                resolver.resolve_fn(params, body, decl);
                             }
-                            Ok(())
-                        })
+                        }
                     };
 
                     if superclass.is_some() {
                         resolver.with_scope(|resolver| {
-                            resolver.scopes.last_mut().unwrap().insert("super".into(), true);
+                            resolver.declare_implicit(Symbol::SUPER);
                             resolve_class(resolver)
                         })
                     } else {
                         resolve_class(resolver)
                     }
-                })?;
+                });
             },
-            Stmt::Expr(expr) | Stmt::Print(expr) => {
-                self.resolve_expr(expr)?;
+            Stmt::Expr(expr) | Stmt::Print(expr, _) => {
+                self.resolve_expr(expr);
             },
             Stmt::Function(name, params, body) => {
-                self.declare(&name)?;
-                self.define(&name);
+                self.declare(name);
+                self.define(name);
 
-                self.resolve_fn(params, body, FunctionType::Function)?;
+                self.resolve_fn(params, body, FunctionType::Function);
             },
-            Stmt::If(cond, then, otherwise) => {
-                self.resolve_expr(cond)?;
-                self.resolve(Rc::make_mut(then))?;
+            Stmt::If(cond, then, otherwise, _) => {
+                self.resolve_expr(cond);
+                self.resolve(Rc::make_mut(then));
                 if let Some(otherwise) = otherwise {
-                    self.resolve(Rc::make_mut(otherwise))?;
+                    self.resolve(Rc::make_mut(otherwise));
                 }
             },
             Stmt::Return(kw, expr) => {
                 match self.function {
                     FunctionType::None => {
-                        return Err(LoxError::parse(kw, "cannot return from top level"));
+                        self.error(kw, ResolveError::TopLevelReturn);
                     },
                     FunctionType::Initializer if expr.is_some() => {
-                        return Err(LoxError::parse(kw, "cannot return value from initializer"));
+                        self.error(kw, ResolveError::InitializerReturn);
                     },
                     _ => {},
                 }
                 if let Some(value) = expr {
-                    self.resolve_expr(value)?;
+                    self.resolve_expr(value);
+                }
+            },
+            Stmt::Import(_, _, name) => {
+                self.declare(name);
+                self.define(name);
+            },
+            Stmt::Throw(_, value) => {
+                self.resolve_expr(value);
+            },
+            Stmt::Try(_, body, catch, finally) => {
+                self.with_scope(|resolver| resolver.resolve_all(body));
+                if let Some((name, body)) = catch {
+                    self.with_scope(|resolver| {
+                        resolver.declare(name);
+                        resolver.define(name);
+                        resolver.resolve_all(body)
+                    });
+                }
+                if let Some(finally) = finally {
+                    self.with_scope(|resolver| resolver.resolve_all(finally));
                 }
             },
             Stmt::Var(name, value) => {
-                self.declare(name)?;
-                self.resolve_expr(value)?;
+                self.declare(name);
+                self.resolve_expr(value);
                 self.define(name);
             },
-            Stmt::While(cond, body) => {
-                self.resolve_expr(cond)?;
-                self.resolve(Rc::make_mut(body))?;
+            Stmt::While(cond, body, inc, _) => {
+                self.resolve_expr(cond);
+                self.with_loop(true, |resolver| resolver.resolve(Rc::make_mut(body)));
+                if let Some(inc) = inc {
+                    self.resolve_expr(inc);
+                }
             },
         }
-        Ok(())
     }
 }
@@ -1,4 +1,7 @@
-use failure::Error;
+use failure::{
+    format_err,
+    Error,
+};
 
 use std::{
     fs::read_to_string,
@@ -12,52 +15,152 @@ use std::{
 
 use crate::*;
 
-pub fn run_file<P>(path: P) -> Result<(), Error>
+/// Which execution engine `run` hands resolved statements to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Walk the AST directly with the `Interpreter`.
+    TreeWalk,
+    /// Compile to a `Chunk` and execute it on the `Vm`.
+    Bytecode,
+}
+
+pub fn run_file<P>(backend: Backend, lints: Lints, path: P) -> Result<(), Error>
 where
     P: AsRef<Path>,
 {
-    let contents = read_to_string(path)?;
+    let file = path.as_ref().display().to_string();
+    let contents =
+        read_to_string(&path).map_err(|e| format_err!("could not read {}: {}", file, e))?;
     let mut interpreter = Interpreter::default();
-    run(false, &mut interpreter, &contents)
+    interpreter.lints = lints;
+    run(false, backend, &mut interpreter, Some(&file), &contents)?;
+    Ok(())
 }
 
-pub fn run_prompt() -> Result<(), Error> {
+pub fn run_prompt(backend: Backend, lints: Lints) -> Result<(), Error> {
     let mut stdin = io::BufReader::new(io::stdin());
     let mut stdout = io::stdout();
 
     let mut interpreter = Interpreter::default();
+    interpreter.lints = lints;
 
     let mut line = String::new();
     loop {
         write!(stdout, "> ")?;
         stdout.flush()?;
-        stdin.read_line(&mut line)?;
-        if let Err(e) = run(true, &mut interpreter, &line) {
-            println!("{}", e);
+        if stdin.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if let Err(e) = run(true, backend, &mut interpreter, None, &line) {
+            if e.exit_code().is_some() {
+                return Err(e.into());
+            }
+            interpreter.report(&e)?;
         }
         line.clear();
     }
 }
 
-pub fn run(interactive: bool, interpreter: &mut Interpreter, source: &str) -> Result<(), Error> {
-    let mut scanner_reporter = Reporter::new();
+pub fn run(
+    interactive: bool,
+    backend: Backend,
+    interpreter: &mut Interpreter,
+    file: Option<&str>,
+    source: &str,
+) -> Result<(), Errors<LoxError>> {
+    let (stmts, warnings) = parse(file, source, interpreter.lints)?;
+    interpreter.warn(warnings).map_err(|e| {
+        LoxError::typecast(format!("could not write warnings: {}", e)).with_kind(ErrorKind::Runtime)
+    })?;
+
+    interpreter.backend = backend;
+    let mut runtime_reporter = Reporter::with_source(file, source);
+    let res = interpreter.limited(|interpreter| {
+        interpreter.in_file(file.map(Path::new), |interpreter| {
+            execute(interactive, backend, interpreter, stmts)
+        })
+    });
+    match res {
+        Err(LoxError::Exit(code)) => return Err(LoxError::Exit(code).into()),
+        Err(err) => runtime_reporter.report(locate(file, err)),
+        Ok(()) => {},
+    }
+    runtime_reporter.finish()?;
+
+    Ok(())
+}
+
+/// The code a script asked to exit with, if `err` came from `exit()`.
+pub fn exit_code(err: &Error) -> Option<i32> {
+    err.downcast_ref::<Errors<LoxError>>().and_then(Errors::exit_code)
+}
+
+/// Scans, parses and resolves `source`, reporting every error found. Also
+/// returns the warnings `lints` gave, if there were no errors.
+pub fn parse(
+    file: Option<&str>,
+    source: &str,
+    lints: Lints,
+) -> Result<(Vec<Stmt>, Reporter<Warning>), Errors<LoxError>> {
+    let mut scanner_reporter = Reporter::with_source(file, source);
     let mut parser_reporter = Reporter::new();
 
-    let scanner = scanner_reporter.filter(scan(source));
+    let scanner =
+        scanner_reporter.filter(scan(source).map(|res| res.map_err(|err| locate(file, err))));
 
-    let mut resolver = Resolver::new();
-    let parser = parser_reporter.filter(
-        Parser::new(scanner).map(|parse_res| parse_res.and_then(|stmt| resolver.analyze(stmt))),
-    );
+    let mut resolver = Resolver::with_lints(lints);
+    let parser = parser_reporter
+        .filter(Parser::new(scanner).map(|parse_res| parse_res.map_err(|err| locate(file, err))))
+        .map(|stmt| resolver.analyze(stmt));
 
     let stmts: Vec<_> = parser.collect();
 
+    let mut warnings = Reporter::with_source(file, source);
+    warnings.join_with(resolver.take_warnings(), |warning| match file {
+        Some(file) => warning.in_file(file),
+        None => warning,
+    });
     scanner_reporter.join(parser_reporter);
+    scanner_reporter.join_with(resolver.into_reporter(), |err| locate(file, err));
     scanner_reporter.finish()?;
+    Ok((stmts, warnings))
+}
+
+fn locate(file: Option<&str>, err: LoxError) -> LoxError {
+    match file {
+        Some(file) => err.in_file(file),
+        None => err,
+    }
+}
+
+pub fn execute(
+    interactive: bool,
+    backend: Backend,
+    interpreter: &mut Interpreter,
+    stmts: Vec<Stmt>,
+) -> Result<(), LoxError> {
+    if backend == Backend::Bytecode {
+        let stmts: Vec<_> = stmts
+            .into_iter()
+            .map(|stmt| match stmt {
+                Stmt::Expr(e) if interactive => {
+                    let span = e.span();
+                    Stmt::Print(e, span)
+                },
+                stmt => stmt,
+            })
+            .collect();
+        let chunk = Compiler::compile(&stmts)?;
+        Vm::new().run(interpreter, chunk.into())?;
+        return Ok(());
+    }
 
     for stmt in stmts {
         match stmt {
-            Stmt::Expr(ref e) if interactive => println!("{}", interpreter.evaluate(e)?),
+            Stmt::Expr(ref e) if interactive => {
+                let value = interpreter.evaluate(e)?;
+                interpreter.print(e.span(), &value)?;
+            },
             _ => {
                 interpreter.execute(&stmt)?;
             },
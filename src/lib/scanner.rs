@@ -15,25 +15,37 @@ pub struct Scanner {
     start:        usize,
     current:      usize,
     line:         usize,
+    /// The column of the next character, counted in characters.
+    column:       usize,
+    start_line:   usize,
+    start_column: usize,
 }
 
 lazy_static! {
     static ref RESERVED_WORDS: HashMap<String, TokenType> = HashMap::from_iter(
         [
             ("and", TokenType::And),
+            ("as", TokenType::As),
+            ("break", TokenType::Break),
+            ("catch", TokenType::Catch),
             ("class", TokenType::Class),
+            ("continue", TokenType::Continue),
             ("else", TokenType::Else),
             ("false", TokenType::False),
+            ("finally", TokenType::Finally),
             ("for", TokenType::For),
             ("fun", TokenType::Fun),
             ("if", TokenType::If),
+            ("import", TokenType::Import),
             ("nil", TokenType::Nil),
             ("or", TokenType::Or),
             ("print", TokenType::Print),
             ("return", TokenType::Return),
             ("super", TokenType::Super),
             ("this", TokenType::This),
+            ("throw", TokenType::Throw),
             ("true", TokenType::True),
+            ("try", TokenType::Try),
             ("var", TokenType::Var),
             ("while", TokenType::While),
         ]
@@ -55,6 +67,9 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
         }
     }
 
@@ -72,17 +87,22 @@ impl Scanner {
 
     fn scan_token(&mut self) -> Result<Token, LoxError> {
         loop {
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column;
             if self.is_at_end() {
                 self.eof_returned = true;
-                return Ok(Token::new(TokenType::Eof, "", (), self.line));
+                return Ok(Token::new(TokenType::Eof, "", (), self.span()));
             }
-            self.start = self.current;
             let ch = self.advance();
             let token = match ch {
                 '(' => self.build_token(TokenType::LeftParen, ()),
                 ')' => self.build_token(TokenType::RightParen, ()),
                 '{' => self.build_token(TokenType::LeftBrace, ()),
                 '}' => self.build_token(TokenType::RightBrace, ()),
+                '[' => self.build_token(TokenType::LeftBracket, ()),
+                ']' => self.build_token(TokenType::RightBracket, ()),
+                ':' => self.build_token(TokenType::Colon, ()),
                 ',' => self.build_token(TokenType::Comma, ()),
                 '.' => self.build_token(TokenType::Dot, ()),
                 '-' => self.build_token(TokenType::Minus, ()),
@@ -117,16 +137,13 @@ impl Scanner {
                 },
                 '/' => self.build_token(TokenType::Slash, ()),
                 ' ' | '\t' | '\r' => continue,
-                '\n' => {
-                    self.line += 1;
-                    continue;
-                },
+                '\n' => continue,
                 '"' => self.string()?,
                 c if is_digit(c) => self.number(),
                 c if is_alpha(c) => self.identifier(),
                 c => {
                     return Err(LoxError::scan(
-                        self.line,
+                        self.span(),
                         format!("unexpected character: {:?}", c as char),
                     ));
                 },
@@ -138,6 +155,12 @@ impl Scanner {
     fn advance(&mut self) -> char {
         let current_char = self.source[self.current..].chars().nth(0).unwrap();
         self.current += current_char.len_utf8();
+        if current_char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
         current_char
     }
 
@@ -146,7 +169,12 @@ impl Scanner {
         P: Into<Primitive>,
     {
         let text = self.source.subtendril(self.start as u32, (self.current - self.start) as u32);
-        Token::new(ty, text, literal, self.line)
+        Token::new(ty, text, literal, self.span())
+    }
+
+    /// The span from the start of the current token to the current position.
+    fn span(&self) -> Span {
+        Span::new(self.start, self.current, self.start_line, self.start_column)
     }
 
     fn peek(&self) -> char {
@@ -159,14 +187,11 @@ impl Scanner {
 
     fn string(&mut self) -> Result<Token, LoxError> {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            }
             self.advance();
         }
 
         if self.is_at_end() {
-            return Err(LoxError::scan(self.line, "unterminated string"));
+            return Err(LoxError::scan(self.span(), "unterminated string"));
         }
 
         self.advance();
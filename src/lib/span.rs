@@ -0,0 +1,68 @@
+use std::{
+    cmp::{
+        max,
+        min,
+    },
+    fmt,
+};
+
+/// A region of source text. `start` and `end` are byte offsets into the
+/// source; `line` and `column` are 1-based and describe `start`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start:  usize,
+    pub end:    usize,
+    pub line:   usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let first = if self.start <= other.start {
+            self
+        } else {
+            other
+        };
+        Span {
+            start:  min(self.start, other.start),
+            end:    max(self.end, other.end),
+            line:   first.line,
+            column: first.column,
+        }
+    }
+}
+
+/// Where an error happened: a span and, when known, the file it points into.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub span: Span,
+}
+
+impl From<Span> for Location {
+    fn from(span: Span) -> Self {
+        Location {
+            file: None,
+            span,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "[{}:{}:{}]", file, self.span.line, self.span.column),
+            None => write!(f, "[line {}]", self.span.line),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::*;
+
+/// Looks up one of the native string methods, bound to `string`. Indices
+/// count characters rather than bytes, and substrings share the original
+/// string's buffer instead of copying it.
+pub fn string_method(string: &LoxStr, name: &str) -> Option<Value> {
+    Some(match name {
+        "len" => bind(string, name, |s: &mut LoxStr| s.chars().count() as f64),
+        "substring" => bind(string, name, |s: &mut LoxStr, start: f64, end: f64| {
+            let len = s.chars().count();
+            let start = char_index(s, start, len + 1).map_err(|e| e.to_string())?;
+            let end = char_index(s, end, len + 1).map_err(|e| e.to_string())?;
+            if start > end {
+                return Err(format!("substring start {} is after end {}", start, end));
+            }
+            Ok(Value::from(substring(s, start, end)))
+        }),
+        "indexOf" => bind(string, name, |s: &mut LoxStr, needle: String| {
+            s.find(&needle).map(|offset| s[..offset].chars().count() as f64).unwrap_or(-1.0)
+        }),
+        "split" => bind(string, name, |s: &mut LoxStr, separator: String| {
+            let parts: Vec<_> = if separator.is_empty() {
+                chars(s).collect()
+            } else {
+                let mut offset = 0;
+                s.split(separator.as_str())
+                    .map(|part| {
+                        let value = Value::from(sub(s, offset, part.len()));
+                        offset += part.len() + separator.len();
+                        value
+                    })
+                    .collect()
+            };
+            Value::List(LoxList::new(parts))
+        }),
+        "trim" => bind(string, name, |s: &mut LoxStr| {
+            let start = s.len() - s.trim_start().len();
+            let end = s.trim_end().len().max(start);
+            Value::from(sub(s, start, end - start))
+        }),
+        "upper" => bind(string, name, |s: &mut LoxStr| s.to_uppercase()),
+        "lower" => bind(string, name, |s: &mut LoxStr| s.to_lowercase()),
+        "replace" => bind(string, name, |s: &mut LoxStr, from: String, to: String| {
+            if from.is_empty() {
+                return Err("cannot replace an empty string".to_string());
+            }
+            Ok(s.replace(&from, &to))
+        }),
+        "startsWith" => bind(string, name, |s: &mut LoxStr, prefix: String| s.starts_with(&prefix)),
+        "endsWith" => bind(string, name, |s: &mut LoxStr, suffix: String| s.ends_with(&suffix)),
+        "chars" => {
+            bind(string, name, |s: &mut LoxStr| Value::List(LoxList::new(chars(s).collect())))
+        },
+        _ => return None,
+    })
+}
+
+/// Indexes `string` by character, giving a one-character string.
+pub fn string_index(string: &LoxStr, index: &Value) -> Result<Value, LoxError> {
+    let len = string.chars().count();
+    let index = *index.primitive().and_then(|p| p.number()).map_err(|_| {
+        LoxError::typecast(format!("string index must be a number, got {}", index))
+    })?;
+    let index = char_index(string, index, len)?;
+    Ok(Value::from(substring(string, index, index + 1)))
+}
+
+fn bind<F, Args>(string: &LoxStr, name: &str, f: F) -> Value
+where
+    F: NativeMethod<LoxStr, Args>,
+{
+    let string = string.clone();
+    let method: LoxStr = name.into();
+    Value::RustFn(
+        RustFn::new(name, f.arity(), move |_, args| {
+            f.call_method(&method, &mut string.clone(), args)
+        })
+        .into(),
+    )
+}
+
+/// Checks that `index` is a whole number below `bound`.
+fn char_index(string: &LoxStr, index: f64, bound: usize) -> Result<usize, LoxError> {
+    if index.fract() != 0.0 {
+        return Err(LoxError::typecast(format!("string index must be an integer, got {}", index)));
+    }
+    if index < 0.0 || index >= bound as f64 {
+        return Err(LoxError::typecast(format!(
+            "index {} out of range for string of length {}",
+            index,
+            string.chars().count()
+        ))
+        .with_kind(ErrorKind::Index));
+    }
+    Ok(index as usize)
+}
+
+/// The characters from `start` up to `end`.
+fn substring(string: &LoxStr, start: usize, end: usize) -> LoxStr {
+    let offset = |idx| {
+        string.char_indices().nth(idx).map(|(offset, _)| offset).unwrap_or(string.len())
+    };
+    let start = offset(start);
+    sub(string, start, offset(end) - start)
+}
+
+/// The `len` bytes from `offset`, sharing `string`'s buffer.
+fn sub(string: &LoxStr, offset: usize, len: usize) -> LoxStr {
+    string.subtendril(offset as u32, len as u32)
+}
+
+fn chars(string: &LoxStr) -> impl Iterator<Item = Value> + '_ {
+    string
+        .char_indices()
+        .map(move |(offset, c)| Value::from(sub(string, offset, c.len_utf8())))
+}
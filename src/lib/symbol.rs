@@ -0,0 +1,132 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    hash::{
+        BuildHasherDefault,
+        Hasher,
+    },
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::*;
+
+/// An interned name: identifiers are interned as they're scanned, so
+/// variables, fields and methods are looked up by comparing integers rather
+/// than hashing their names. Symbols index the interner of the thread they
+/// were made on, so they can't be sent to another.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32, PhantomData<*const ()>);
+
+/// A map keyed by symbols, which hashes them as the integers they are.
+pub type SymbolMap<V> = HashMap<Symbol, V, BuildHasherDefault<SymbolHasher>>;
+
+/// Fibonacci hashing of a symbol's index. Anything else hashed with it is
+/// folded in a byte at a time.
+#[derive(Default)]
+pub struct SymbolHasher(u64);
+
+const GOLDEN_RATIO: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Hasher for SymbolHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0.rotate_left(8) ^ u64::from(byte)).wrapping_mul(GOLDEN_RATIO);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = (self.0 ^ u64::from(n)).wrapping_mul(GOLDEN_RATIO);
+    }
+}
+
+/// The names interned on this thread, freed when the thread exits.
+struct Interner {
+    names:   Vec<Rc<str>>,
+    symbols: HashMap<Rc<str>, Symbol>,
+}
+
+/// Names the interpreter looks up itself, interned up front in this order.
+const PREINTERNED: [&str; 4] = ["", "init", "this", "super"];
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner {
+        names:   PREINTERNED.iter().map(|&name| name.into()).collect(),
+        symbols: PREINTERNED
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (name.into(), Symbol::new(i)))
+            .collect(),
+    });
+}
+
+impl Symbol {
+    /// The symbol of the empty string, given to tokens that aren't names.
+    pub const EMPTY: Symbol = Symbol::new(0);
+    pub const INIT: Symbol = Symbol::new(1);
+    pub const THIS: Symbol = Symbol::new(2);
+    pub const SUPER: Symbol = Symbol::new(3);
+
+    const fn new(index: usize) -> Symbol {
+        Symbol(index as u32, PhantomData)
+    }
+
+    pub fn intern(name: &str) -> Symbol {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(&symbol) = interner.symbols.get(name) {
+                return symbol;
+            }
+            let name: Rc<str> = name.into();
+            let symbol = Symbol::new(interner.names.len());
+            interner.names.push(name.clone());
+            interner.symbols.insert(name, symbol);
+            symbol
+        })
+    }
+
+    pub fn name(self) -> Rc<str> {
+        INTERNER.with(|interner| interner.borrow().names[self.0 as usize].clone())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.name())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<&LoxStr> for Symbol {
+    fn from(name: &LoxStr) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<LoxStr> for Symbol {
+    fn from(name: LoxStr) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
@@ -9,6 +9,9 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -34,19 +37,27 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    As,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -57,22 +68,29 @@ pub enum TokenType {
 pub struct Token {
     pub ty:      TokenType,
     pub lexeme:  LoxStr,
+    /// The interned lexeme, or the empty symbol for literals.
+    pub symbol:  Symbol,
     pub literal: Primitive,
-    pub line:    usize,
+    pub span:    Span,
 }
 
 impl Token {
-    pub fn new<S, P>(ty: TokenType, lexeme: S, literal: P, line: usize) -> Token
+    pub fn new<S, P>(ty: TokenType, lexeme: S, literal: P, span: Span) -> Token
     where
         S: Into<LoxStr>,
         P: Into<Primitive>,
     {
-        let lexeme = lexeme.into();
+        let lexeme: LoxStr = lexeme.into();
+        let symbol = match ty {
+            TokenType::String | TokenType::Number => Symbol::EMPTY,
+            _ => Symbol::intern(&lexeme),
+        };
         Token {
             ty,
-            lexeme: lexeme.into(),
+            lexeme,
+            symbol,
             literal: literal.into(),
-            line,
+            span,
         }
     }
 }
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::*;
+
+/// An active call, as tracked by the `Interpreter` while it runs.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub callee:    Value,
+    /// Where the call was made, or `None` if host code made it.
+    pub call_site: Option<Span>,
+}
+
+impl CallFrame {
+    fn function(&self) -> String {
+        self.callee.callable().map(|c| c.name().to_string()).unwrap_or_default()
+    }
+
+    fn is_native(&self) -> bool {
+        matches!(self.callee, Value::RustFn(_))
+    }
+}
+
+/// One line of a `StackTrace`: where execution was in a given function.
+/// `span` is `None` for native functions and `function` is `None` for the
+/// top-level script. A line with neither stands for host code that called
+/// into the script with `Interpreter::call_function`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceLine {
+    pub span:     Option<Span>,
+    pub function: Option<String>,
+}
+
+impl TraceLine {
+    const HOST: TraceLine = TraceLine {
+        span:     None,
+        function: None,
+    };
+}
+
+/// Repeats of a trace line shown before the rest are summarized.
+const MAX_REPEATS: usize = 2;
+
+/// The call stack at the point a runtime error was raised, innermost first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StackTrace(pub Vec<TraceLine>);
+
+impl StackTrace {
+    /// Captures `frames` for an error raised at `span` in the innermost one.
+    pub fn capture(frames: &[CallFrame], span: Span) -> Self {
+        let mut lines = vec![];
+        let mut position = Some(span);
+        for frame in frames.iter().rev() {
+            lines.push(TraceLine {
+                span:     if frame.is_native() { None } else { position },
+                function: Some(frame.function()),
+            });
+            position = frame.call_site;
+            if position.is_none() {
+                lines.push(TraceLine::HOST);
+            }
+        }
+        if position.is_some() {
+            lines.push(TraceLine {
+                span:     position,
+                function: None,
+            });
+        }
+        StackTrace(lines)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The lines to show for this trace, with long runs of the same line, as
+    /// left by deep recursion, cut short.
+    pub fn rendered(&self) -> Vec<String> {
+        let mut rendered = vec![];
+        let mut lines = self.0.iter().peekable();
+        while let Some(line) = lines.next() {
+            let mut repeats = 0;
+            while lines.peek() == Some(&line) {
+                lines.next();
+                repeats += 1;
+            }
+            let shown = repeats.min(MAX_REPEATS);
+            for _ in 0..=shown {
+                rendered.push(line.to_string());
+            }
+            if repeats > shown {
+                rendered.push(format!("[previous line repeated {} more times]", repeats - shown));
+            }
+        }
+        rendered
+    }
+}
+
+impl fmt::Display for TraceLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == TraceLine::HOST {
+            return write!(f, "[host]");
+        }
+        match self.span {
+            Some(span) => write!(f, "[line {}]", span.line)?,
+            None => write!(f, "[native]")?,
+        }
+        match &self.function {
+            Some(function) => write!(f, " in {}()", function),
+            None => write!(f, " in script"),
+        }
+    }
+}
+
+impl fmt::Display for StackTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.rendered() {
+            write!(f, "\n{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ use crate::*;
 use either::Either;
 
 use std::{
+    cell::RefCell,
+    convert::TryFrom,
     fmt,
     rc::Rc,
 };
@@ -14,6 +16,9 @@ pub enum Value {
     RustFn(Rc<dyn Callable>),
     Class(Rc<LoxClass>),
     Instance(LoxInstance),
+    List(LoxList),
+    Map(LoxMap),
+    Module(LoxModule),
 }
 
 impl From<Primitive> for Value {
@@ -22,6 +27,83 @@ impl From<Primitive> for Value {
     }
 }
 
+impl From<f64> for Value {
+    fn from(other: f64) -> Self {
+        Value::Primitive(other.into())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(other: bool) -> Self {
+        Value::Primitive(other.into())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(other: &str) -> Self {
+        allocate(other.len());
+        Value::Primitive(Primitive::String(other.into()))
+    }
+}
+
+impl From<String> for Value {
+    fn from(other: String) -> Self {
+        allocate(other.len());
+        Value::Primitive(Primitive::String(other.into()))
+    }
+}
+
+impl From<LoxStr> for Value {
+    fn from(other: LoxStr) -> Self {
+        Value::Primitive(Primitive::String(other))
+    }
+}
+
+impl From<()> for Value {
+    fn from(_other: ()) -> Self {
+        Value::Primitive(Primitive::Nil)
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = LoxError;
+
+    fn try_from(value: Value) -> Result<Self, LoxError> {
+        Ok(*value.primitive()?.number()?)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = LoxError;
+
+    fn try_from(value: Value) -> Result<Self, LoxError> {
+        Ok(*value.primitive()?.boolean()?)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = LoxError;
+
+    fn try_from(value: Value) -> Result<Self, LoxError> {
+        Ok(value.primitive()?.string()?.to_string())
+    }
+}
+
+/// `nil` converts to `None`; anything else must convert to `T`.
+impl<T> TryFrom<Value> for Option<T>
+where
+    T: TryFrom<Value, Error = LoxError>,
+{
+    type Error = LoxError;
+
+    fn try_from(value: Value) -> Result<Self, LoxError> {
+        match value {
+            Value::Primitive(Primitive::Nil) => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Primitive {
     Nil,
@@ -62,6 +144,9 @@ impl PartialEq<Value> for Value {
             (Value::LoxFn(l), Value::LoxFn(r)) => Rc::ptr_eq(l, r),
             (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
             (Value::Instance(l), Value::Instance(r)) => l == r,
+            (Value::List(l), Value::List(r)) => l == r,
+            (Value::Map(l), Value::Map(r)) => l == r,
+            (Value::Module(l), Value::Module(r)) => l == r,
             _ => false,
         }
     }
@@ -84,7 +169,7 @@ impl fmt::Display for Primitive {
         match self {
             Primitive::Nil => write!(f, "nil"),
             Primitive::String(v) => write!(f, "{}", v),
-            Primitive::Number(v) => write!(f, "{}", v),
+            Primitive::Number(v) => format_number(f, *v),
             Primitive::Bool(v) => write!(f, "{}", v),
         }
     }
@@ -98,30 +183,80 @@ impl fmt::Display for Value {
             Value::LoxFn(c) => c.fmt(f),
             Value::Class(c) => c.fmt(f),
             Value::Instance(i) => i.fmt(f),
+            Value::List(l) => l.fmt(f),
+            Value::Map(m) => m.fmt(f),
+            Value::Module(m) => m.fmt(f),
         }
     }
 }
 
+thread_local! {
+    static DISPLAYING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Displays a container with `body`, or with `placeholder` if the container
+/// at `ptr` is already being displayed further up, so that self-referencing
+/// containers print finitely.
+pub fn display_once<F>(f: &mut fmt::Formatter, ptr: usize, placeholder: &str, body: F) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter) -> fmt::Result,
+{
+    if DISPLAYING.with(|d| d.borrow().contains(&ptr)) {
+        return write!(f, "{}", placeholder);
+    }
+    DISPLAYING.with(|d| d.borrow_mut().push(ptr));
+    let res = body(f);
+    DISPLAYING.with(|d| d.borrow_mut().pop());
+    res
+}
+
 macro_rules! cast_fn {
-    ($fn_name:ident, $outer:ident, $variant:ident, $ret:ty) => {
+    ($fn_name:ident, $outer:ident, $variant:ident, $ret:ty, $expected:expr) => {
         #[allow(dead_code)]
         pub fn $fn_name(&self) -> Result<&$ret, LoxError> {
             match self {
                 $outer::$variant(inner) => Ok(inner),
-                _ => Err(LoxError::typecast(format!("cast failed, expecting {}, actually is {:?}", stringify!($variant), self))),
+                _ => Err(LoxError::typecast(format!(
+                    "expected {}, got {}",
+                    $expected,
+                    self.type_name()
+                ))),
             }
         }
-    }
+    };
 }
 
 impl Primitive {
-    cast_fn!(number, Primitive, Number, f64);
-    cast_fn!(boolean, Primitive, Bool, bool);
-    cast_fn!(string, Primitive, String, LoxStr);
+    cast_fn!(number, Primitive, Number, f64, "a number");
+    cast_fn!(boolean, Primitive, Bool, bool, "a bool");
+    cast_fn!(string, Primitive, String, LoxStr, "a string");
+
+    /// The name of this value's type, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Primitive::Nil => "nil",
+            Primitive::Bool(_) => "bool",
+            Primitive::Number(_) => "number",
+            Primitive::String(_) => "string",
+        }
+    }
 }
 
 impl Value {
-    cast_fn!(primitive, Value, Primitive, Primitive);
+    cast_fn!(primitive, Value, Primitive, Primitive, "nil, a bool, a number or a string");
+
+    /// The name of this value's type, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Primitive(primitive) => primitive.type_name(),
+            Value::LoxFn(_) | Value::RustFn(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Module(_) => "module",
+        }
+    }
 
     pub fn callable<'s>(&'s self) -> Result<impl Callable + 's, LoxError> {
         Ok(match self {
@@ -130,8 +265,8 @@ impl Value {
             Value::Class(inner) => Either::Right(Either::Left::<_, Rc<dyn Callable>>(inner)),
             _ => {
                 return Err(LoxError::typecast(format!(
-                    "cast failed, expecting class or function, actually is {:?}",
-                    self
+                    "expected a class or function, got {}",
+                    self.type_name()
                 )))
             },
         })
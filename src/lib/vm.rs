@@ -0,0 +1,474 @@
+use crate::*;
+
+use std::rc::Rc;
+
+#[derive(Debug)]
+struct Frame {
+    function: Option<Rc<LoxFn>>,
+    chunk:    Rc<Chunk>,
+    ip:       usize,
+    base:     usize,
+    env:      Environment,
+}
+
+/// An installed `try` handler and the state to restore when it catches.
+#[derive(Debug)]
+struct Handler {
+    frame:   usize,
+    stack:   usize,
+    calls:   usize,
+    pending: usize,
+    env:     Environment,
+    target:  usize,
+    finally: bool,
+}
+
+/// A stack machine that executes `Chunk`s produced by the `Compiler`. It
+/// shares globals, environments and natives with the `Interpreter` it runs
+/// against, so compiled and tree-walked code can call each other freely.
+#[derive(Debug, Default)]
+pub struct Vm {
+    stack:    Vec<Value>,
+    frames:   Vec<Frame>,
+    handlers: Vec<Handler>,
+    /// Errors being held while a `finally` block runs, with the value
+    /// thrown if they came from a `throw`.
+    pending:  Vec<(LoxError, Option<Value>)>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm::default()
+    }
+
+    /// Runs `chunk` to completion in the interpreter's current environment,
+    /// returning the value of its top-level `Return`.
+    pub fn run(&mut self, interp: &mut Interpreter, chunk: Rc<Chunk>) -> Result<Value, LoxError> {
+        let env = interp.environment.clone();
+        let base = self.stack.len();
+        let depth = self.frames.len();
+        let handlers = self.handlers.len();
+        let pending = self.pending.len();
+        self.frames.push(Frame {
+            function: None,
+            chunk,
+            ip: 0,
+            base,
+            env: env.clone(),
+        });
+
+        let res = self.execute(interp, depth);
+
+        res.map_err(|err| {
+            let err = err.with_trace(&interp.frames);
+            let calls = self.frames.len() - depth - 1;
+            interp.frames.truncate(interp.frames.len() - calls);
+            interp.environment = env;
+            self.stack.truncate(base);
+            self.frames.truncate(depth);
+            self.handlers.truncate(handlers);
+            self.pending.truncate(pending);
+            err
+        })
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().unwrap()
+    }
+
+    fn span(&self) -> Span {
+        let frame = self.frame();
+        frame.chunk.spans[frame.ip - 1]
+    }
+
+    fn error<S>(&self, msg: S) -> LoxError
+    where
+        S: Into<String>,
+    {
+        LoxError::runtime_at(self.span(), msg)
+    }
+
+    fn name(&self, idx: usize) -> Symbol {
+        self.frame().chunk.names[idx]
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().expect("stack underflow")
+    }
+
+    fn execute(&mut self, interp: &mut Interpreter, depth: usize) -> Result<Value, LoxError> {
+        loop {
+            match self.step(interp, depth) {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {},
+                Err(err) => self.catch(interp, depth, err)?,
+            }
+        }
+    }
+
+    /// Hands `err` to the innermost handler installed by this run, unwinding
+    /// to the frame that installed it, or returns it if there is none.
+    fn catch(
+        &mut self,
+        interp: &mut Interpreter,
+        depth: usize,
+        err: LoxError,
+    ) -> Result<(), LoxError> {
+        let handler = match self.handlers.last() {
+            Some(handler) if handler.frame >= depth && err.is_catchable() => {
+                self.handlers.pop().unwrap()
+            },
+            _ => return Err(err),
+        };
+        let err = err.with_trace(&interp.frames);
+
+        interp.frames.truncate(handler.calls);
+        interp.environment = handler.env;
+        self.frames.truncate(handler.frame + 1);
+        self.stack.truncate(handler.stack);
+        self.frames.last_mut().unwrap().ip = handler.target;
+
+        self.pending.truncate(handler.pending);
+        if handler.finally {
+            self.pending.push((err, interp.thrown.take()));
+        } else {
+            let value = interp.error_value(&err);
+            self.push(value);
+        }
+        Ok(())
+    }
+
+    /// Executes a single instruction, returning the value of the run's
+    /// top-level `Return` once it's reached.
+    fn step(&mut self, interp: &mut Interpreter, depth: usize) -> Result<Option<Value>, LoxError> {
+        let op = {
+            let frame = self.frames.last_mut().unwrap();
+            frame.ip += 1;
+            frame.chunk.code[frame.ip - 1]
+        };
+        interp.tick(|| self.span())?;
+
+        match op {
+            Op::Constant(idx) => {
+                let value = self.frame().chunk.constants[idx].clone();
+                self.push(value);
+            },
+            Op::Nil => self.push(Primitive::Nil.into()),
+            Op::True => self.push(Primitive::Bool(true).into()),
+            Op::False => self.push(Primitive::Bool(false).into()),
+            Op::Pop => {
+                self.pop();
+            },
+            Op::Define(name) => {
+                let value = self.pop();
+                interp.environment.define(self.name(name), value);
+            },
+            Op::GetVar(name, slot) => {
+                let name = self.name(name);
+                let value = interp.environment.get_at(name, slot).ok_or_else(|| {
+                    self.error(format!("Undefined variable: {}", name)).with_kind(ErrorKind::Name)
+                })?;
+                self.push(value);
+            },
+            Op::SetVar(name, slot) => {
+                let name = self.name(name);
+                let value = self.peek().clone();
+                if interp.environment.assign_at(name, value, slot).is_none() {
+                    let err = self.error(format!("variable {} is not defined", name));
+                    return Err(err.with_kind(ErrorKind::Name));
+                }
+            },
+            Op::GetProperty(name) => {
+                let name = self.name(name);
+                let object = self.pop();
+                let value = get_property(object, name).map_err(|e| e.or_at(self.span()))?;
+                self.push(value);
+            },
+            Op::SetProperty(name) => {
+                let value = self.pop();
+                if let Value::Instance(instance) = self.pop() {
+                    instance
+                        .set_property(self.name(name), value.clone())
+                        .map_err(|e| e.or_at(self.span()))?;
+                    self.push(value);
+                } else {
+                    return Err(self.error("only instances have fields").with_kind(ErrorKind::Type));
+                }
+            },
+            Op::GetSuper(method, slot) => {
+                let superclass = match interp.environment.get_at(Symbol::SUPER, slot) {
+                    Some(Value::Class(superclass)) => superclass,
+                    _ => return Err(self.error("could not find superclass (interpreter bug)")),
+                };
+                let this = slot.map(Slot::this);
+                let this = match interp.environment.get_at(Symbol::THIS, this) {
+                    Some(Value::Instance(this)) => this,
+                    _ => return Err(self.error("could not find 'this' (interpreter bug)")),
+                };
+                let method = self.name(method);
+                let bound = superclass
+                    .find_method(&this, method)
+                    .ok_or_else(|| {
+                        let err = self.error(format!("undefined method: {}", method));
+                        err.with_kind(ErrorKind::Name)
+                    })?;
+                self.push(bound);
+            },
+            Op::GetIndex => {
+                let index = self.pop();
+                let object = self.pop();
+                let value = get_index(object, index).map_err(|e| e.or_at(self.span()))?;
+                self.push(value);
+            },
+            Op::SetIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                let object = self.pop();
+                set_index(object, index, value.clone()).map_err(|e| e.or_at(self.span()))?;
+                self.push(value);
+            },
+            Op::List(n_elements) => {
+                let elements = self.stack.split_off(self.stack.len() - n_elements);
+                self.push(Value::List(LoxList::new(elements)));
+            },
+            Op::Map(n_entries) => {
+                let entries = self.stack.split_off(self.stack.len() - 2 * n_entries);
+                let map = LoxMap::new();
+                for entry in entries.chunks(2) {
+                    map.set(&entry[0], entry[1].clone()).map_err(|e| e.or_at(self.span()))?;
+                }
+                self.push(Value::Map(map));
+            },
+            Op::Binary(ty) => {
+                let right = self.pop();
+                let left = self.pop();
+                let value = binary(ty, left, right).map_err(|e| e.or_at(self.span()))?;
+                self.push(value);
+            },
+            Op::Unary(ty) => {
+                let right = self.pop();
+                let value = unary(ty, right).map_err(|e| e.or_at(self.span()))?;
+                self.push(value);
+            },
+            Op::Print => {
+                let value = self.pop();
+                interp.print(self.span(), &value)?;
+            },
+            Op::Jump(target) => {
+                if target < self.frame().ip {
+                    // A loop's back edge.
+                    maybe_collect();
+                    interp.check_memory(self.span())?;
+                }
+                self.frames.last_mut().unwrap().ip = target;
+            },
+            Op::JumpIfFalse(target) => {
+                if !is_truthy(self.peek()) {
+                    self.frames.last_mut().unwrap().ip = target;
+                }
+            },
+            Op::PushScope => {
+                interp.environment = Environment::with_enclosing(&interp.environment);
+            },
+            Op::PopScope => {
+                interp.environment = interp.environment.get_enclosing().unwrap();
+            },
+            Op::Call(n_args) => {
+                let args = self.stack.split_off(self.stack.len() - n_args);
+                let callee = self.pop();
+                self.call(interp, callee, args)?;
+            },
+            Op::GetMethod(name, cache) => {
+                let name = self.name(name);
+                let object = self.pop();
+                let chunk = self.frame().chunk.clone();
+                match get_method(object, name, &chunk.caches[cache]) {
+                    Ok(Callee::Method(this, method)) => {
+                        self.push(Value::LoxFn(method));
+                        self.push(Value::Instance(this));
+                    },
+                    Ok(Callee::Value(value)) => {
+                        self.push(value);
+                        self.push(Primitive::Nil.into());
+                    },
+                    Err(e) => return Err(e.or_at(self.span())),
+                }
+            },
+            Op::Invoke(n_args) => {
+                let args = self.stack.split_off(self.stack.len() - n_args);
+                let this = self.pop();
+                let callee = self.pop();
+                match (this, callee) {
+                    (Value::Instance(this), Value::LoxFn(method)) => {
+                        self.invoke(interp, this, method, args)?
+                    },
+                    (_, callee) => self.call(interp, callee, args)?,
+                }
+            },
+            Op::Closure(idx) => {
+                let function = self.frame().chunk.functions[idx].clone();
+                let closure = LoxFn::from_static(function, interp.environment.clone());
+                self.push(Value::LoxFn(closure.into()));
+            },
+            Op::Class(idx) => self.class(interp, idx)?,
+            Op::Catch(target) | Op::Finally(target) => {
+                self.handlers.push(Handler {
+                    frame: self.frames.len() - 1,
+                    stack: self.stack.len(),
+                    calls: interp.frames.len(),
+                    pending: self.pending.len(),
+                    env: interp.environment.clone(),
+                    target,
+                    finally: matches!(op, Op::Finally(_)),
+                });
+            },
+            Op::Import(path) => {
+                let path = self.name(path);
+                let module = interp.import(self.span(), &path.name())?;
+                self.push(Value::Module(module));
+            },
+            Op::PopHandler => {
+                self.handlers.pop();
+            },
+            Op::Throw => {
+                let value = self.pop();
+                return Err(interp.throw(self.span(), value));
+            },
+            Op::Rethrow => {
+                let (err, thrown) = self.pending.pop().expect("no pending error");
+                interp.thrown = thrown;
+                return Err(err);
+            },
+            Op::DropPending => {
+                self.pending.pop().expect("no pending error");
+            },
+            Op::Return => {
+                let value = self.pop();
+                let frame = self.frames.pop().unwrap();
+                if frame.function.is_some() {
+                    interp.frames.pop();
+                }
+                let value = frame
+                    .function
+                    .and_then(|function| function.init_result(&interp.environment))
+                    .unwrap_or(value);
+                interp.environment = frame.env;
+                self.stack.truncate(frame.base);
+                if self.frames.len() == depth {
+                    return Ok(Some(value));
+                }
+                self.push(value);
+            },
+        }
+        Ok(None)
+    }
+
+    fn call(
+        &mut self,
+        interp: &mut Interpreter,
+        callee: Value,
+        args: Vec<Value>,
+    ) -> Result<(), LoxError> {
+        // Compiled functions get a new frame on this machine rather than a
+        // nested `Vm`; everything else goes through `Callable`.
+        maybe_collect();
+        interp.check_memory(self.span())?;
+        if let Value::LoxFn(function) = &callee {
+            if function.chunk().is_some() {
+                return self.enter(interp, function.clone(), None, args);
+            }
+        }
+
+        let value = interp.call(callee, args, self.span())?;
+        self.push(value);
+        Ok(())
+    }
+
+    /// Calls `method` on `this` without binding it first.
+    fn invoke(
+        &mut self,
+        interp: &mut Interpreter,
+        this: LoxInstance,
+        method: Rc<LoxFn>,
+        args: Vec<Value>,
+    ) -> Result<(), LoxError> {
+        maybe_collect();
+        interp.check_memory(self.span())?;
+        if method.chunk().is_some() {
+            return self.enter(interp, method, Some(this), args);
+        }
+
+        let value = interp.call_method(this, method, args, self.span())?;
+        self.push(value);
+        Ok(())
+    }
+
+    /// Pushes a frame running the compiled `function`.
+    fn enter(
+        &mut self,
+        interp: &mut Interpreter,
+        function: Rc<LoxFn>,
+        this: Option<LoxInstance>,
+        args: Vec<Value>,
+    ) -> Result<(), LoxError> {
+        let callee = Value::LoxFn(function.clone());
+        interp.check_arity(&callee, args.len(), self.span())?;
+        interp.check_depth(self.span())?;
+        interp.frames.push(CallFrame {
+            callee,
+            call_site: Some(self.span()),
+        });
+        let env = function.call_env(this, args);
+        self.frames.push(Frame {
+            chunk:    function.chunk().unwrap().clone(),
+            function: Some(function),
+            ip:       0,
+            base:     self.stack.len(),
+            env:      std::mem::replace(&mut interp.environment, env),
+        });
+        Ok(())
+    }
+
+    fn class(&mut self, interp: &mut Interpreter, idx: usize) -> Result<(), LoxError> {
+        let chunk = self.frame().chunk.clone();
+        let proto = &chunk.classes[idx];
+
+        let superclass = if let Some(name) = &proto.superclass {
+            if let Value::Class(superclass) = self.pop() {
+                Some(superclass)
+            } else {
+                let err = LoxError::runtime(name, "superclass must be a class");
+                return Err(err.with_kind(ErrorKind::Type));
+            }
+        } else {
+            None
+        };
+
+        let mut class_environment = interp.environment.clone();
+        if let Some(superclass) = superclass.clone() {
+            class_environment = Environment::with_enclosing(&class_environment);
+            class_environment.define(Symbol::SUPER, Value::Class(superclass));
+        }
+
+        let methods: SymbolMap<_> = proto
+            .methods
+            .iter()
+            .map(|method| {
+                let function = LoxFn::from_static(method.clone(), class_environment.clone());
+                (method.name().symbol, Rc::new(function))
+            })
+            .collect();
+
+        let class = LoxClass::new(proto.name.lexeme.clone(), superclass, methods);
+        interp.environment.define(proto.name.symbol, Value::Class(class.into()));
+        Ok(())
+    }
+}
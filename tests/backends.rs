@@ -0,0 +1,107 @@
+//! Runs the same programs on the tree-walker and the VM, which must print
+//! the same thing and stop with the same errors.
+
+mod common;
+
+use common::*;
+
+const PROGRAMS: &[&str] = &[
+    "print (1 + 2) * 3; print 10 - 4; print 7 / 2;",
+    "print -(3 - 5) * 2;",
+    "print \"con\" + \"cat\";",
+    "print 1 < 2; print 2 <= 1; print 3 > 3; print 3 >= 3;",
+    "print 1 == 1; print \"a\" != \"b\"; print nil == false; print !nil;",
+    "print nil or \"default\"; print false or false; print 0 or 1;",
+    "var a = 1; { var a = 2; print a; } print a;",
+    "var i = 0; while (i < 3) { print i; i = i + 1; }",
+    "for (var i = 0; i < 3; i = i + 1) print i * i;",
+    "
+    fun fib(n) {
+        if (n < 2) return n;
+        return fib(n - 1) + fib(n - 2);
+    }
+    print fib(15);
+    ",
+    "
+    fun counter() {
+        var count = 0;
+        fun increment() {
+            count = count + 1;
+            return count;
+        }
+        return increment;
+    }
+    var a = counter();
+    var b = counter();
+    print a(); print a(); print b();
+    ",
+    "
+    var fns = nil;
+    for (var i = 0; i < 3; i = i + 1) {
+        fun show() { print i; }
+        if (fns == nil) fns = show;
+    }
+    fns();
+    ",
+    "
+    class Point {
+        init(x, y) {
+            this.x = x;
+            this.y = y;
+        }
+        sum() { return this.x + this.y; }
+    }
+    var p = Point(1, 2);
+    print p.sum();
+    p.x = 10;
+    print p.sum();
+    print p;
+    print Point;
+    ",
+    "
+    class Animal {
+        init(name) { this.name = name; }
+        speak() { return this.name + \" makes a sound\"; }
+    }
+    class Dog < Animal {
+        speak() { return super.speak() + \": woof\"; }
+    }
+    print Dog(\"Rex\").speak();
+    var speak = Dog(\"Fido\").speak;
+    print speak();
+    ",
+    "fun f() {} print f(); print f; print clock() > 0;",
+    "print 1 + nil;",
+    "print undefined;",
+    "fun f(a) {} f(1, 2);",
+    "var x = 1; x();",
+    "class A {} A().missing;",
+];
+
+#[test]
+fn backends_agree() {
+    for program in PROGRAMS {
+        run_both(program);
+    }
+}
+
+#[test]
+fn runs_programs() {
+    assert_eq!(output("print (1 + 2) * 3; print 10 - 4; print 7 / 2;"), "9\n6\n3.5\n");
+    assert_eq!(output("var a = 1; { var a = 2; print a; } print a;"), "2\n1\n");
+    assert_eq!(output(PROGRAMS[9]), "610\n");
+    assert_eq!(output(PROGRAMS[10]), "1\n2\n1\n");
+    assert_eq!(output(PROGRAMS[13]), "Rex makes a sound: woof\nFido makes a sound: woof\n");
+}
+
+#[test]
+fn reports_runtime_errors() {
+    assert_eq!(error("print 1 + nil;"), "[line 1] Error: requires two numbers or two strings");
+    assert_eq!(error("print undefined;"), "[line 1] Error: Undefined variable: undefined");
+}
+
+#[test]
+fn and_short_circuits_on_a_falsy_left_operand() {
+    assert_eq!(output("print nil and 1; print false and undefined;"), "nil\nfalse\n");
+    assert_eq!(output("print 1 and 2; print true and nil;"), "2\nnil\n");
+}
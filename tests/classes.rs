@@ -0,0 +1,57 @@
+//! Classes, instances and initializers.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn initializers_return_the_instance() {
+    let source = "
+    class A {
+        init(x) {
+            this.x = x;
+            return;
+        }
+    }
+    var a = A(1);
+    print a.x;
+    print a.init(2) == a;
+    print a.x;
+    ";
+    assert_eq!(output(source), "1\ntrue\n2\n");
+}
+
+#[test]
+fn initializers_propagate_errors() {
+    let source = "class A { init() { print 1 + nil; } } print A();";
+    assert_eq!(run_both(source).0, "");
+    assert_eq!(
+        error(source),
+        "[line 1] Error: requires two numbers or two strings\n[line 1] in A()\n[line 1] in script"
+    );
+
+    let source = "
+    class A { init() { throw \"no\"; print \"after\"; } }
+    try { A(); } catch (e) { print e; }
+    ";
+    assert_eq!(output(source), "no\n");
+}
+
+#[test]
+fn inherits_initializers() {
+    let source = "
+    class A {
+        init(x) { this.x = x; }
+    }
+    class B < A {}
+    class C < B {
+        get() { return this.x; }
+    }
+    print C(1).get();
+    print C(2) == nil;
+    ";
+    assert_eq!(output(source), "1\nfalse\n");
+
+    let err = error("class A { init(x) {} } class B < A {} B();");
+    assert_eq!(err.lines().next().unwrap(), "[line 1] Error: expected 1 arguments but got 0");
+}
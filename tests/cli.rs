@@ -0,0 +1,84 @@
+//! The `rlox` binary: where it reports errors and the codes it exits with.
+
+use std::{
+    fs,
+    process::{
+        Command,
+        Output,
+    },
+};
+
+/// Writes `source` to a script named `name`, returning its path.
+fn script(name: &str, source: &str) -> String {
+    let file = format!("rlox-cli-{}-{}.lox", name, std::process::id());
+    let path = std::env::temp_dir().join(file);
+    fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+/// Writes `source` to a script named `name` and runs `rlox` on it.
+fn run_script(name: &str, source: &str) -> Output {
+    run_args(&[&script(name, source)])
+}
+
+/// Runs `rlox` with `args`.
+fn run_args(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn runs_scripts() {
+    let output = run_script("ok", "print 1 + 1;");
+    assert_eq!(stdout(&output), "2\n");
+    assert_eq!(stderr(&output), "");
+    assert!(output.status.success());
+}
+
+#[test]
+fn reports_errors_on_stderr() {
+    for (name, source) in &[("runtime", "print 1;\nprint nil + 1;"), ("parse", "print (;")] {
+        let output = run_script(name, source);
+        assert!(!output.status.success(), "{} error exited successfully", name);
+        assert!(!stdout(&output).contains("Error"), "{}", stdout(&output));
+        assert!(stderr(&output).contains("error"), "{}", stderr(&output));
+    }
+}
+
+#[test]
+fn exits_with_the_scripts_code() {
+    let output = run_script("exit", "print 1; exit(3);");
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    for args in &[&["--allow=bogus"][..], &["--frobnicate"], &["a.lox", "b.lox"]] {
+        let output = run_args(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert_eq!(stdout(&output), "", "{:?}", args);
+        assert!(!stderr(&output).is_empty(), "{:?}", args);
+    }
+    assert_eq!(stderr(&run_args(&["--allow=bogus"])), "unknown lint: bogus\n");
+    assert!(stderr(&run_args(&["--frobnicate"])).starts_with("Usage: rlox"));
+}
+
+#[test]
+fn allows_lints_by_name() {
+    let path = script("lint", "fun f() {\n  return;\n  print 1;\n}");
+    let output = run_args(&[&path]);
+    assert!(stderr(&output).contains("unreachable_code"), "{}", stderr(&output));
+    assert!(output.status.success());
+
+    let output = run_args(&["--allow=unreachable_code", &path]);
+    assert_eq!(stderr(&output), "");
+    assert!(output.status.success());
+}
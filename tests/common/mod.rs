@@ -0,0 +1,64 @@
+//! Helpers shared by the integration tests. Each test crate uses only some
+//! of them.
+#![allow(dead_code)]
+
+use std::io;
+
+use rlox::{
+    Backend,
+    Errors,
+    Interpreter,
+    LoxError,
+    OutputBuffer,
+};
+
+pub const BACKENDS: [Backend; 2] = [Backend::TreeWalk, Backend::Bytecode];
+
+/// Runs `source` on a new interpreter using `backend`, returning what it
+/// printed and the error it stopped with, if any.
+pub fn run(backend: Backend, source: &str) -> (String, Option<String>) {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    run_in(&mut interp, source)
+}
+
+/// Runs `source` on `interp`, returning what it printed and the error it
+/// stopped with, if any.
+pub fn run_in(interp: &mut Interpreter, source: &str) -> (String, Option<String>) {
+    let output = OutputBuffer::new();
+    let stdout = interp.set_output(output.clone());
+    let stderr = interp.set_error_output(io::sink());
+    let res = interp.eval(source);
+    interp.set_output(stdout);
+    interp.set_error_output(stderr);
+    (output.contents(), res.err().map(|err| message(&err)))
+}
+
+/// The first error in `errors`, without the diagnostic rendering.
+pub fn message(errors: &Errors<LoxError>) -> String {
+    errors.errors()[0].to_string()
+}
+
+/// Runs `source` on both backends, checking that they agree.
+pub fn run_both(source: &str) -> (String, Option<String>) {
+    let tree_walk = run(Backend::TreeWalk, source);
+    let bytecode = run(Backend::Bytecode, source);
+    assert_eq!(tree_walk, bytecode, "the backends disagree on:\n{}", source);
+    tree_walk
+}
+
+/// What `source` prints on both backends, which must run it without error.
+pub fn output(source: &str) -> String {
+    match run_both(source) {
+        (output, None) => output,
+        (_, Some(err)) => panic!("unexpected error: {}\nin:\n{}", err, source),
+    }
+}
+
+/// The error `source` stops with on both backends.
+pub fn error(source: &str) -> String {
+    match run_both(source) {
+        (_, Some(err)) => err,
+        (output, None) => panic!("no error, printed {:?}\nin:\n{}", output, source),
+    }
+}
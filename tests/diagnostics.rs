@@ -0,0 +1,104 @@
+//! Checks how errors and warnings are rendered: the header, the quoted
+//! source line, the underline and the notes beneath it.
+
+mod common;
+
+use rlox::{
+    parse,
+    Interpreter,
+    Lints,
+};
+
+/// `text` without the terminal colors it gets when stdout is a terminal.
+fn plain(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[test]
+fn renders_errors_with_the_source_line() {
+    let errors = parse(Some("main.lox"), "var a = 1;\nprint a +;\n", Lints::none())
+        .expect_err("no error");
+    assert_eq!(
+        plain(&errors.to_string()),
+        "\
+error[E0002]: expect expression
+ --> main.lox:2:10
+  |
+2 | print a +;
+  |          ^ at \";\""
+    );
+}
+
+#[test]
+fn separates_several_errors() {
+    let errors = parse(None, "print @;\nprint #;", Lints::none()).expect_err("no error");
+    let rendered = plain(&errors.to_string());
+    assert_eq!(rendered.matches("error[").count(), errors.errors().len());
+    assert!(rendered.contains("\n\nerror["), "{}", rendered);
+    assert!(!rendered.ends_with('\n'), "{:?}", rendered);
+}
+
+#[test]
+fn renders_secondary_labels_and_help() {
+    let source = "var x = 1;\nfun f(x) {\n  var y;\n  return x;\n}";
+    let (_, warnings) = parse(Some("main.lox"), source, Lints::all()).unwrap();
+    let warnings = warnings.finish().expect_err("no warnings");
+    assert_eq!(
+        plain(&warnings.to_string()),
+        "\
+warning[shadowed_parameter]: parameter `x` shadows an outer variable
+ --> main.lox:2:7
+  |
+1 | var x = 1;
+  |     - the outer variable is declared here
+2 | fun f(x) {
+  |       ^
+
+warning[unused_variable]: unused variable `y`
+ --> main.lox:3:7
+  |
+3 |   var y;
+  |       ^
+  = help: prefix it with an underscore if that's intended: `_y`"
+    );
+}
+
+#[test]
+fn renders_runtime_errors_with_a_backtrace() {
+    let source = "fun g() { return 1 + nil; }\nfun h() { g(); }\nh();";
+    let errors = Interpreter::new().eval(source).expect_err("no error");
+    assert_eq!(
+        plain(&errors.to_string()),
+        "\
+error[E0004]: requires two numbers or two strings
+ --> <input>:1:18
+  |
+1 | fun g() { return 1 + nil; }
+  |                  ^^^^^^^
+  = backtrace:
+      [line 1] in g()
+      [line 2] in h()
+      [line 3] in script"
+    );
+}
+
+#[test]
+fn omits_the_snippet_without_the_source() {
+    // Spans that don't match the source they're rendered with, as happens
+    // for lines from an earlier REPL entry, only print their location.
+    let mut interp = Interpreter::new();
+    common::run_in(&mut interp, "fun f() { return 1 + nil; }");
+    let errors = interp.eval("\nf();").expect_err("no error");
+    let rendered = plain(&errors.to_string());
+    assert!(rendered.starts_with("error[E0004]: requires two numbers or two strings\n"));
+    assert!(!rendered.contains("return 1 + nil"), "{}", rendered);
+}
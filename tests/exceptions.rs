@@ -0,0 +1,162 @@
+//! `throw` and `try`/`catch`/`finally`, and the values errors are caught as.
+
+mod common;
+
+use common::*;
+
+/// Defines `kind(e)`, naming the built-in error class `e` is an instance of.
+const KIND: &str = "
+fun kind(e) {
+    if (isInstance(e, TypeError)) return \"TypeError\";
+    if (isInstance(e, NameError)) return \"NameError\";
+    if (isInstance(e, ArityError)) return \"ArityError\";
+    if (isInstance(e, IndexError)) return \"IndexError\";
+    if (isInstance(e, RuntimeError)) return \"RuntimeError\";
+    return \"not an error\";
+}
+";
+
+#[test]
+fn catches_thrown_values() {
+    let source = "
+    try {
+        throw \"oops\";
+        print \"unreachable\";
+    } catch (e) {
+        print e;
+    }
+    try { throw 1; } catch (e) { print e + 1; }
+    ";
+    assert_eq!(output(source), "oops\n2\n");
+}
+
+#[test]
+fn catches_runtime_errors_with_message_and_line() {
+    let source = "
+    try {
+        print nil + 1;
+    } catch (e) {
+        print e.message;
+        print e.line;
+        print e;
+    }
+    ";
+    assert_eq!(output(source), "requires two numbers or two strings\n3\n<instance TypeError>\n");
+}
+
+#[test]
+fn catches_type_errors_with_their_line() {
+    let source = "
+    try {
+        -\"a\";
+    } catch (e) {
+        print e.message;
+        print e.line;
+    }
+    try {
+        var notCallable = [1];
+        notCallable();
+    } catch (e) {
+        print e.message;
+        print e.line;
+    }
+    ";
+    let expected = "expected a number, got string\n3\nexpected a class or function, got list\n10\n";
+    assert_eq!(output(source), expected);
+}
+
+#[test]
+fn gives_each_kind_of_error_its_own_class() {
+    let cases = [
+        ("1 + nil;", "TypeError"),
+        ("3();", "TypeError"),
+        ("var B = 1; class A < B {}", "TypeError"),
+        ("str(1, 2);", "ArityError"),
+        ("fun f(a) { return a; } f();", "ArityError"),
+        ("undefined;", "NameError"),
+        ("undefined = 1;", "NameError"),
+        ("class A {} A().x;", "NameError"),
+        ("[].nope;", "NameError"),
+        ("math.nope;", "NameError"),
+        ("[1][3];", "IndexError"),
+        ("[].pop();", "IndexError"),
+        ("var m = {}; m[\"x\"];", "IndexError"),
+        ("readFile(\"/nonexistent/file\");", "RuntimeError"),
+    ];
+    for (statement, class) in &cases {
+        let source = format!("{}try {{ {} }} catch (e) {{ print kind(e); }}", KIND, statement);
+        assert_eq!(output(&source), format!("{}\n", class), "in {:?}", statement);
+    }
+}
+
+#[test]
+fn error_classes_share_a_hierarchy() {
+    let source = "
+    print isInstance(TypeError(\"a\"), RuntimeError);
+    print isInstance(TypeError(\"a\"), Error);
+    print isInstance(RuntimeError(\"a\"), TypeError);
+    print isInstance(1, Error);
+    class Mine < IndexError {}
+    try { throw Mine(\"custom\"); } catch (e) { print kind(e) + \": \" + e.message; }
+    ";
+    let source = format!("{}{}", KIND, source);
+    assert_eq!(output(&source), "true\ntrue\nfalse\nfalse\nIndexError: custom\n");
+}
+
+#[test]
+fn runs_finally_blocks() {
+    let source = "
+    try { print \"body\"; } finally { print \"finally\"; }
+    try {
+        try { throw \"inner\"; } finally { print \"cleanup\"; }
+    } catch (e) {
+        print \"caught \" + e;
+    }
+    fun f() {
+        try { return \"returned\"; } finally { print \"on return\"; }
+    }
+    print f();
+    ";
+    assert_eq!(
+        output(source),
+        "body\nfinally\ncleanup\ncaught inner\non return\nreturned\n"
+    );
+}
+
+#[test]
+fn drops_errors_when_finally_jumps_away() {
+    // A `finally` block that breaks or returns discards the error it was
+    // running for, which mustn't then be rethrown by another one.
+    let source = "
+    try {
+        try {
+            throw \"outer\";
+        } finally {
+            while (true) {
+                try { throw \"inner\"; } finally { break; }
+            }
+        }
+    } catch (e) {
+        print e;
+    }
+    fun f() {
+        try { throw \"dropped\"; } finally { return \"returned\"; }
+    }
+    print f();
+    try {
+        try { throw \"second\"; } finally { print f(); }
+    } catch (e) {
+        print e;
+    }
+    ";
+    assert_eq!(output(source), "outer\nreturned\nreturned\nsecond\n");
+}
+
+#[test]
+fn reports_uncaught_exceptions() {
+    assert_eq!(error("throw \"up\";"), "[line 1] Error: uncaught exception: up");
+    assert_eq!(
+        error("throw NameError(\"no such thing\");"),
+        "[line 1] Error: uncaught exception: NameError: no such thing"
+    );
+}
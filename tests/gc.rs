@@ -0,0 +1,64 @@
+//! The cycle collector: what it frees and what it must leave alone.
+
+mod common;
+
+use rlox::{
+    collect_garbage,
+    gc_stats,
+    set_gc_threshold,
+    Interpreter,
+};
+
+use common::*;
+
+/// Defines `make(name)`, which returns an instance caught in two cycles: one
+/// through its own field and one through a closure over the call's scope.
+const MAKE: &str = "
+class Node {}
+fun make(name) {
+    var node = Node();
+    node.name = name;
+    node.me = node;
+    fun greet() { return \"hi \" + node.name; }
+    node.greet = greet;
+    return node;
+}
+";
+
+#[test]
+fn frees_unreachable_cycles() {
+    for &backend in &BACKENDS {
+        // The heap is shared by the thread, so clear out what the last
+        // interpreter left first.
+        set_gc_threshold(None);
+        collect_garbage();
+        let mut interp = Interpreter::new();
+        interp.backend = backend;
+        let source = format!(
+            "{}
+            var kept = make(\"kept\");
+            for (var i = 0; i < 100; i = i + 1) make(i);
+            ",
+            MAKE
+        );
+        let (_, err) = run_in(&mut interp, &source);
+        assert_eq!(err, None);
+        let stats = collect_garbage();
+        assert_eq!(stats.last_freed, 200, "on {:?}", backend);
+
+        let (output, err) = run_in(&mut interp, "print kept.me.greet();");
+        assert_eq!((output.as_str(), err), ("hi kept\n", None));
+        assert_eq!(collect_garbage().last_freed, 0, "on {:?}", backend);
+    }
+}
+
+#[test]
+fn collects_as_objects_are_allocated() {
+    set_gc_threshold(Some(50));
+    let before = gc_stats();
+    let source = format!("{}for (var i = 0; i < 100; i = i + 1) make(i);", MAKE);
+    run_both(&source);
+    let after = gc_stats();
+    assert!(after.collections > before.collections, "{:?}", after);
+    assert!(after.total_freed > before.total_freed, "{:?}", after);
+}
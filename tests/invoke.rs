@@ -0,0 +1,154 @@
+//! Method calls through the invoke fast path and its per-call-site cache,
+//! which must behave exactly like looking the method up and then calling it.
+
+mod common;
+
+use rlox::{
+    collect_garbage,
+    gc_stats,
+    set_gc_threshold,
+    Interpreter,
+};
+
+use common::*;
+
+const ANIMALS: &str = "
+class Animal {
+    init(name) { this.name = name; }
+    speak() { return this.name + \" makes a sound\"; }
+}
+class Dog < Animal {
+    speak() { return this.name + \" barks\"; }
+}
+class Puppy < Dog {}
+class Cat < Animal {
+    speak() { return super.speak() + \" and meows\"; }
+}
+fun speak(animal) { return animal.speak(); }
+";
+
+#[test]
+fn dispatches_on_each_receivers_class() {
+    // One call site sees a different class almost every time.
+    let source = format!(
+        "{}
+        var animals = [Dog(\"rex\"), Cat(\"tom\"), Animal(\"gen\"), Puppy(\"bit\"), Dog(\"max\")];
+        for (var i = 0; i < animals.len(); i = i + 1) print speak(animals[i]);
+        for (var i = 0; i < animals.len(); i = i + 1) print animals[i].speak();
+        ",
+        ANIMALS
+    );
+    let once = "rex barks\ntom makes a sound and meows\ngen makes a sound\nbit barks\nmax barks\n";
+    assert_eq!(output(&source), once.repeat(2));
+}
+
+#[test]
+fn prefers_fields_over_methods() {
+    let source = format!(
+        "{}
+        fun quiet() {{ return \"...\"; }}
+        var a = Dog(\"a\");
+        var b = Dog(\"b\");
+        print speak(a);
+        b.speak = quiet;
+        print speak(b);
+        print speak(a);
+        a.speak = quiet;
+        print speak(a);
+        ",
+        ANIMALS
+    );
+    assert_eq!(output(&source), "a barks\n...\na barks\n...\n");
+}
+
+#[test]
+fn binds_this_to_each_receiver() {
+    let source = "
+    class Counter {
+        init() { this.n = 0; }
+        add() {
+            this.n = this.n + 1;
+            return this.n;
+        }
+    }
+    var a = Counter();
+    var b = Counter();
+    for (var i = 0; i < 3; i = i + 1) {
+        a.add();
+        if (i == 0) b.add();
+    }
+    print a.n;
+    print b.n;
+    ";
+    assert_eq!(output(source), "3\n1\n");
+}
+
+#[test]
+fn keeps_bound_methods_bound() {
+    let source = format!(
+        "{}
+        var rex = Dog(\"rex\");
+        var bark = rex.speak;
+        rex.name = \"max\";
+        print bark();
+        var cat = Cat(\"tom\");
+        bark = cat.speak;
+        print bark();
+        ",
+        ANIMALS
+    );
+    assert_eq!(output(&source), "max barks\ntom makes a sound and meows\n");
+}
+
+#[test]
+fn checks_arity_at_cached_sites() {
+    let source = "
+    class A { m(x) { return x; } }
+    class B { m() { return \"b\"; } }
+    fun call(o) { return o.m(1); }
+    print call(A());
+    print call(B());
+    ";
+    let (output, err) = run_both(source);
+    assert_eq!(output, "1\n");
+    let err = err.unwrap();
+    assert_eq!(err.lines().next().unwrap(), "[line 4] Error: expected 0 arguments but got 1");
+}
+
+#[test]
+fn rejects_receivers_that_are_not_instances() {
+    let source = format!("{}print speak(Dog(\"a\"));\nprint speak(1);", ANIMALS);
+    let (output, err) = run_both(&source);
+    assert_eq!(output, "a barks\n");
+    // Line 13 is the call in `speak`.
+    let err = err.unwrap();
+    assert_eq!(err.lines().next().unwrap(), "[line 13] Error: only instances have fields");
+}
+
+#[test]
+fn allocates_one_scope_per_method_call() {
+    let setup = "
+    class C { m(x) { return x; } }
+    fun f(x) { return x; }
+    var c = C();
+    var bound = c.m;
+    ";
+    // Calls to methods, bound or not, allocate no more than function calls.
+    let calls = ["f(i);", "c.m(i);", "bound(i);"];
+    for &backend in &BACKENDS {
+        set_gc_threshold(None);
+        let mut interp = Interpreter::new();
+        interp.backend = backend;
+        assert_eq!(run_in(&mut interp, setup), (String::new(), None));
+        let allocated: Vec<_> = calls
+            .iter()
+            .map(|call| {
+                collect_garbage();
+                let source = format!("for (var i = 0; i < 100; i = i + 1) {}", call);
+                assert_eq!(run_in(&mut interp, &source), (String::new(), None));
+                gc_stats().allocated
+            })
+            .collect();
+        assert_eq!(allocated, [allocated[0]; 3], "on {:?}", backend);
+    }
+}
@@ -0,0 +1,108 @@
+//! The natives for files, input and exiting, and the capabilities that
+//! switch them off.
+
+mod common;
+
+use std::{
+    fs,
+    path::PathBuf,
+};
+
+use rlox::{
+    Capabilities,
+    Interpreter,
+};
+
+use common::*;
+
+/// A fresh, empty directory for `test`.
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rlox-io-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn reads_writes_and_lists_files() {
+    for &backend in &BACKENDS {
+        let dir = temp_dir(&format!("files-{:?}", backend));
+        let source = format!(
+            "
+            var dir = \"{}\";
+            writeFile(dir + \"/b.txt\", \"one\");
+            appendFile(dir + \"/b.txt\", \" two\");
+            appendFile(dir + \"/a.txt\", \"new\");
+            print readFile(dir + \"/b.txt\");
+            print readFile(dir + \"/a.txt\");
+            print listDir(dir);
+            ",
+            dir.display()
+        );
+        let expected = "one two\nnew\n[a.txt, b.txt]\n";
+        assert_eq!(run(backend, &source), (expected.into(), None));
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "one two");
+    }
+}
+
+#[test]
+fn raises_catchable_errors_for_missing_files() {
+    let source = "
+    try {
+        readFile(\"/nonexistent/file\");
+    } catch (e) {
+        print isInstance(e, RuntimeError);
+        print e.message.startsWith(\"could not read /nonexistent/file: \");
+    }
+    ";
+    assert_eq!(output(source), "true\ntrue\n");
+}
+
+#[test]
+fn exits_with_the_code_given() {
+    for &backend in &BACKENDS {
+        let mut interp = Interpreter::new();
+        interp.backend = backend;
+        let source = "print 1; try { exit(3); } catch (e) { print \"caught\"; } print 2;";
+        let errors = interp.eval(source).unwrap_err();
+        assert_eq!(errors.exit_code(), Some(3));
+    }
+    let (output, err) = run_both("print 1; exit(3); print 2;");
+    assert_eq!((output.as_str(), err.unwrap().as_str()), ("1\n", "exit(3)"));
+    let err = error("exit(1.5);");
+    assert_eq!(
+        err.lines().next().unwrap(),
+        "[line 1] Error: exit() expects an integer for argument 1, got 1.5"
+    );
+}
+
+#[test]
+fn disables_natives_without_the_capability() {
+    let cases = [
+        ("readLine();", "readLine() is disabled"),
+        ("readFile(\"x\");", "readFile() is disabled"),
+        ("writeFile(\"x\", \"\");", "writeFile() is disabled"),
+        ("appendFile(\"x\", \"\");", "appendFile() is disabled"),
+        ("listDir(\".\");", "listDir() is disabled"),
+        ("exit(0);", "exit() is disabled"),
+    ];
+    for &backend in &BACKENDS {
+        for (source, message) in &cases {
+            let mut interp = Interpreter::new();
+            interp.backend = backend;
+            interp.capabilities = Capabilities::none();
+            let err = run_in(&mut interp, source).1.unwrap();
+            let first = err.lines().next().unwrap();
+            assert_eq!(first, format!("[line 1] Error: {}", message), "in {:?}", source);
+        }
+    }
+}
+
+#[test]
+fn checks_each_capability_separately() {
+    let mut interp = Interpreter::new();
+    interp.capabilities.filesystem = false;
+    let source = "try { readFile(\"x\"); } catch (e) { print e.message; } exit(4);";
+    let (output, err) = run_in(&mut interp, source);
+    assert_eq!((output.as_str(), err.unwrap().as_str()), ("readFile() is disabled\n", "exit(4)"));
+}
@@ -0,0 +1,158 @@
+//! The `Limits` on how much work scripts may do, and stopping them from
+//! another thread.
+
+mod common;
+
+use std::{
+    thread,
+    time::Duration,
+};
+
+use rlox::{
+    Backend,
+    Interpreter,
+    Limits,
+};
+
+use common::*;
+
+/// A new interpreter for `backend` with `limits`.
+fn limited(backend: Backend, limits: Limits) -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    interp.limits = limits;
+    interp
+}
+
+/// The first line of the error `source` stops with on `interp`.
+fn stopped(interp: &mut Interpreter, source: &str) -> String {
+    let (_, err) = run_in(interp, source);
+    let err = err.unwrap_or_else(|| panic!("no error in:\n{}", source));
+    err.lines().next().unwrap().to_string()
+}
+
+const FOREVER: &str = "
+try {
+    while (true) {}
+} catch (e) {
+    print \"caught\";
+}";
+
+#[test]
+fn halts_after_too_many_steps() {
+    for &backend in &BACKENDS {
+        let limits = Limits {
+            max_steps: Some(1000),
+            ..Limits::default()
+        };
+        let mut interp = limited(backend, limits);
+        let err = stopped(&mut interp, FOREVER);
+        assert_eq!(err, "[line 3] Error: step limit of 1000 exceeded", "on {:?}", backend);
+
+        // The count starts over with each run.
+        let (output, err) = run_in(&mut interp, "for (var i = 0; i < 50; i = i + 1) {} print 1;");
+        assert_eq!((output.as_str(), err), ("1\n", None), "on {:?}", backend);
+    }
+}
+
+#[test]
+fn locates_halts_in_loops() {
+    for &backend in &BACKENDS {
+        let limits = Limits {
+            max_steps: Some(100),
+            ..Limits::default()
+        };
+        let errors = limited(backend, limits).eval("\n\nwhile (true) {}").unwrap_err();
+        let location = errors.errors()[0].location().unwrap();
+        assert_eq!(location.span.line, 3, "on {:?}", backend);
+        assert!(location.span.column > 0, "on {:?}", backend);
+    }
+}
+
+#[test]
+fn limits_call_depth() {
+    let source = "
+fun deep(n) {
+    if (n == 0) return \"bottom\";
+    return deep(n - 1);
+}
+print deep(40);
+try { deep(100); } catch (e) { print e.message; }
+deep(100);";
+    for &backend in &BACKENDS {
+        let limits = Limits {
+            max_depth: Some(50),
+            ..Limits::default()
+        };
+        let (output, err) = run_in(&mut limited(backend, limits), source);
+        assert_eq!(output, "bottom\nstack overflow\n", "on {:?}", backend);
+        let err = err.unwrap();
+        assert_eq!(err.lines().next().unwrap(), "[line 4] Error: stack overflow");
+    }
+}
+
+#[test]
+fn overflows_cleanly_with_the_default_depth() {
+    // Test threads have small stacks, which the default depth would overrun
+    // without the check on the stack itself.
+    let source = "fun deep(n) { return deep(n + 1); } deep(0);";
+    for &backend in &BACKENDS {
+        let err = stopped(&mut limited(backend, Limits::default()), source);
+        assert_eq!(err, "[line 1] Error: stack overflow", "on {:?}", backend);
+    }
+}
+
+#[test]
+fn overflows_cleanly_without_a_depth_limit() {
+    let overflow = thread::Builder::new()
+        .stack_size(1 << 20)
+        .spawn(|| {
+            let source = "fun deep(n) { return deep(n + 1); } deep(0);";
+            stopped(&mut limited(Backend::TreeWalk, Limits::none()), source)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(overflow, "[line 1] Error: stack overflow");
+}
+
+#[test]
+fn times_out() {
+    for &backend in &BACKENDS {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Limits::default()
+        };
+        let err = stopped(&mut limited(backend, limits), FOREVER);
+        assert_eq!(err, "[line 3] Error: timed out", "on {:?}", backend);
+    }
+}
+
+#[test]
+fn stops_when_interrupted() {
+    for &backend in &BACKENDS {
+        let mut interp = limited(backend, Limits::default());
+        let handle = interp.interrupt_handle();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.interrupt();
+        });
+        let err = stopped(&mut interp, FOREVER);
+        assert_eq!(err, "[line 3] Error: interrupted", "on {:?}", backend);
+        interrupter.join().unwrap();
+
+        // An interrupt stops only one run.
+        let (output, err) = run_in(&mut interp, "print 1;");
+        assert_eq!((output.as_str(), err), ("1\n", None), "on {:?}", backend);
+    }
+}
+
+#[test]
+fn stops_the_next_run_if_none_is_running() {
+    for &backend in &BACKENDS {
+        let mut interp = limited(backend, Limits::default());
+        interp.interrupt_handle().interrupt();
+        let err = stopped(&mut interp, "print 1;");
+        assert_eq!(err, "[line 1] Error: interrupted", "on {:?}", backend);
+    }
+}
@@ -0,0 +1,143 @@
+//! The warnings the resolver gives for code that runs but probably doesn't
+//! do what was meant, and allowing them.
+
+use rlox::{
+    parse,
+    Lint,
+    Lints,
+    Warning,
+};
+
+/// The warnings `source` gets with `lints`, as the lint and the line it
+/// points at.
+fn warnings_with(source: &str, lints: Lints) -> Vec<(Lint, usize)> {
+    let (_, warnings) = parse(None, source, lints).unwrap();
+    match warnings.finish() {
+        Ok(()) => vec![],
+        Err(warnings) => {
+            let warning = |warning: &Warning| (warning.lint, warning.location.span.line);
+            warnings.errors().iter().map(warning).collect()
+        },
+    }
+}
+
+fn warnings(source: &str) -> Vec<(Lint, usize)> {
+    warnings_with(source, Lints::all())
+}
+
+/// Unreachable code warnings on each of `lines`.
+fn unreachable(lines: &[usize]) -> Vec<(Lint, usize)> {
+    lines.iter().map(|&line| (Lint::UnreachableCode, line)).collect()
+}
+
+#[test]
+fn warns_about_unused_names() {
+    let source = "
+    {
+        var a = 1;
+        var _b = 2;
+    }
+    fun f(x, _y) {}
+    f(1, 2);
+    ";
+    assert_eq!(warnings(source), [(Lint::UnusedVariable, 3), (Lint::UnusedParameter, 6)]);
+}
+
+#[test]
+fn warns_once_about_code_after_an_exit() {
+    let source = "
+    fun f(x) {
+        while (x) {
+            break;
+            print x;
+        }
+        while (x) {
+            continue;
+            print x;
+        }
+        if (x) throw x;
+        return x;
+        print x;
+        print x;
+    }
+    ";
+    assert_eq!(warnings(source), unreachable(&[5, 9, 13]));
+}
+
+#[test]
+fn follows_exits_through_branches_and_blocks() {
+    let source = "
+    fun branches(c) {
+        if (c) return 1; else return 2;
+        print c;
+    }
+    fun nested(c) {
+        {
+            if (c) { throw c; } else { return; }
+        }
+        print c;
+    }
+    fun tried(c) {
+        try { return c; } catch (e) { throw e; }
+        print c;
+    }
+    ";
+    assert_eq!(warnings(source), unreachable(&[4, 10, 14]));
+}
+
+#[test]
+fn points_at_the_statement_control_leaves_through() {
+    let source = "fun f(c) {\n  if (c) return 1;\n  else return 2;\n  print c;\n}";
+    let (_, warnings) = parse(None, source, Lints::all()).unwrap();
+    let warnings = warnings.finish().unwrap_err();
+    let (span, _) = warnings.errors()[0].related.clone().unwrap();
+    assert_eq!(&source[span.start..span.end], "if (c) return 1;\n  else return 2;");
+}
+
+#[test]
+fn allows_code_that_may_run() {
+    let source = "
+    fun f(c) {
+        if (c) return 1;
+        while (c) return 2;
+        if (c) {} else return 3;
+        try { return 4; } catch (_e) {}
+        { if (c) return 5; }
+        return 6;
+    }
+    ";
+    assert_eq!(warnings(source), []);
+}
+
+#[test]
+fn warns_about_shadowing_and_self_reference() {
+    let source = "
+    var a = a;
+    fun f(b) {
+        fun g(b) { return b; }
+        return g(b);
+    }
+    ";
+    assert_eq!(warnings(source), [(Lint::SelfReference, 2), (Lint::ShadowedParameter, 4)]);
+}
+
+#[test]
+fn silences_allowed_lints() {
+    let source = "fun f(x) {\n  var y;\n  return;\n  print x;\n}";
+    assert_eq!(warnings(source), [(Lint::UnusedVariable, 2), (Lint::UnreachableCode, 4)]);
+
+    let mut lints = Lints::all();
+    lints.allow(Lint::UnreachableCode);
+    assert_eq!(warnings_with(source, lints), [(Lint::UnusedVariable, 2)]);
+    lints.allow(Lint::UnusedVariable).warn(Lint::UnreachableCode);
+    assert_eq!(warnings_with(source, lints), [(Lint::UnreachableCode, 4)]);
+    assert_eq!(warnings_with(source, Lints::none()), []);
+}
+
+#[test]
+fn names_each_lint() {
+    for &lint in &Lint::ALL {
+        assert_eq!(lint.name().parse::<Lint>(), Ok(lint));
+    }
+    assert_eq!("bogus".parse::<Lint>(), Err("unknown lint: bogus".to_string()));
+}
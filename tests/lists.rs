@@ -0,0 +1,76 @@
+//! List literals, indexing and the native list methods.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn builds_and_indexes_lists() {
+    let source = "
+    var l = [1, \"a\", nil, [2, 3]];
+    print l;
+    print l[3][1];
+    l[0] = l[0] + 10;
+    print l[0];
+    print (l[1] = \"b\");
+    print l;
+    print [];
+    ";
+    assert_eq!(output(source), "[1, a, nil, [2, 3]]\n3\n11\nb\n[11, b, nil, [2, 3]]\n[]\n");
+}
+
+#[test]
+fn shares_lists_by_reference() {
+    let source = "
+    var l = [1];
+    var m = l;
+    m.push(2);
+    print l;
+    print l == m;
+    print [1] == [1];
+    ";
+    assert_eq!(output(source), "[1, 2]\ntrue\nfalse\n");
+}
+
+#[test]
+fn runs_native_methods() {
+    let source = "
+    var l = [1, 2, 3];
+    l.push(4);
+    print l.len();
+    print l.pop();
+    l.insert(0, 0);
+    l.insert(4, 9);
+    print l;
+    print l.remove(1);
+    print l.slice(1, 3);
+    print l.slice(0, 0);
+    print l;
+    ";
+    assert_eq!(output(source), "4\n4\n[0, 1, 2, 3, 9]\n1\n[2, 3]\n[]\n[0, 2, 3, 9]\n");
+}
+
+#[test]
+fn prints_self_referencing_lists() {
+    assert_eq!(output("var l = [1]; l.push(l); print l;"), "[1, [...]]\n");
+}
+
+#[test]
+fn rejects_bad_indices() {
+    let cases = [
+        ("print [1][1.5];", "list index must be an integer, got 1.5"),
+        ("print [1][\"a\"];", "list index must be a number, got a"),
+        ("print [1][-1];", "index -1 out of range for list of length 1"),
+        ("print [1][1];", "index 1 out of range for list of length 1"),
+        ("[1][1] = 2;", "index 1 out of range for list of length 1"),
+        ("[].pop();", "pop from empty list"),
+        ("[1, 2].slice(2, 1);", "slice start 2 is after end 1"),
+        ("var x = 1; print x[0];", "only lists, maps and strings can be indexed"),
+        ("[1].nope;", "undefined list method: nope"),
+    ];
+    for (source, message) in &cases {
+        let err = error(source);
+        let first = err.lines().next().unwrap();
+        assert_eq!(first, format!("[line 1] Error: {}", message), "in {:?}", source);
+    }
+}
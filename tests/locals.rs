@@ -0,0 +1,136 @@
+//! Local variables, which live in slots numbered in declaration order. Each
+//! test checks that the slots the resolver hands out line up with the order
+//! the backends define variables in.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn shadows_in_nested_blocks() {
+    let source = "
+    {
+        var a = 1;
+        {
+            var b = 2;
+            var a = 3;
+            print a + b;
+        }
+        var c = 4;
+        print a;
+        print c;
+    }
+    ";
+    assert_eq!(output(source), "5\n1\n4\n");
+}
+
+#[test]
+fn keeps_captured_slots_as_scopes_grow() {
+    let source = "
+    {
+        var a = \"a\";
+        fun getA() { return a; }
+        var b = \"b\";
+        fun getB() { return b; }
+        a = \"A\";
+        var c = getA() + getB();
+        print c;
+    }
+    ";
+    assert_eq!(output(source), "Ab\n");
+}
+
+#[test]
+fn puts_parameters_before_locals() {
+    let source = "
+    fun f(x, y) {
+        var z = x + y;
+        {
+            var w = z * 2;
+            print w;
+        }
+        print x;
+        print y;
+        print z;
+    }
+    f(1, 2);
+    ";
+    assert_eq!(output(source), "6\n1\n2\n3\n");
+}
+
+#[test]
+fn gives_each_iteration_its_own_slots() {
+    // The second iteration skips the declarations, which mustn't shift the
+    // slots of the third.
+    let source = "
+    var fs = [];
+    for (var i = 0; i < 3; i = i + 1) {
+        if (i == 1) continue;
+        var j = i * 10;
+        fun f() { return j; }
+        fs.push(f);
+    }
+    print fs[0]();
+    print fs[1]();
+    ";
+    assert_eq!(output(source), "0\n20\n");
+}
+
+#[test]
+fn binds_this_and_super() {
+    let source = "
+    class A {
+        init(x) { this.x = x; }
+        get() {
+            var local = this.x;
+            return local;
+        }
+    }
+    class B < A {
+        get() {
+            var extra = \"b\";
+            return extra + super.get();
+        }
+    }
+    print B(\"1\").get();
+    ";
+    assert_eq!(output(source), "b1\n");
+}
+
+#[test]
+fn declares_around_try_and_catch() {
+    let source = "
+    {
+        var before = \"before\";
+        try {
+            var inside = \"inside\";
+            throw inside;
+        } catch (e) {
+            var caught = e;
+            print before + \" \" + caught;
+        }
+        var after = \"after\";
+        print before + \" \" + after;
+    }
+    ";
+    assert_eq!(output(source), "before inside\nbefore after\n");
+}
+
+#[test]
+fn defines_local_functions_and_classes() {
+    let source = "
+    {
+        fun fib(n) {
+            if (n < 2) return n;
+            return fib(n - 1) + fib(n - 2);
+        }
+        class Counter {
+            init() { this.n = 0; }
+        }
+        var c = Counter();
+        print fib(10);
+        print c.n;
+    }
+    ";
+    assert_eq!(output(source), "55\n0\n");
+}
@@ -0,0 +1,89 @@
+//! `break` and `continue` in `while` and `for` loops.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn breaks_out_of_loops() {
+    let source = "
+    var i = 0;
+    while (true) {
+        if (i == 3) break;
+        print i;
+        i = i + 1;
+    }
+    for (var j = 0; j < 10; j = j + 1) {
+        if (j == 2) break;
+        print j;
+    }
+    print \"done\";
+    ";
+    assert_eq!(output(source), "0\n1\n2\n0\n1\ndone\n");
+}
+
+#[test]
+fn continues_with_the_increment() {
+    let source = "
+    for (var i = 0; i < 5; i = i + 1) {
+        if (i == 1) continue;
+        if (i == 3) continue;
+        print i;
+    }
+    var j = 0;
+    while (j < 4) {
+        j = j + 1;
+        if (j == 2) continue;
+        print j;
+    }
+    ";
+    assert_eq!(output(source), "0\n2\n4\n1\n3\n4\n");
+}
+
+#[test]
+fn affects_only_the_innermost_loop() {
+    let source = "
+    for (var i = 0; i < 3; i = i + 1) {
+        for (var j = 0; j < 3; j = j + 1) {
+            if (j == 1) continue;
+            if (j == 2) break;
+            print i + j;
+        }
+    }
+    ";
+    assert_eq!(output(source), "0\n1\n2\n");
+}
+
+#[test]
+fn leaves_block_scopes() {
+    // Locals declared in the loop body must be popped on the way out.
+    let source = "
+    var a = \"outer\";
+    for (var i = 0; i < 3; i = i + 1) {
+        var b = i;
+        {
+            var c = b * 2;
+            if (c == 2) continue;
+            if (c == 4) break;
+            print c;
+        }
+    }
+    print a;
+    ";
+    assert_eq!(output(source), "0\nouter\n");
+}
+
+#[test]
+fn closes_over_locals_left_by_break() {
+    let source = "
+    var f;
+    while (true) {
+        var x = \"captured\";
+        fun g() { print x; }
+        f = g;
+        break;
+    }
+    f();
+    ";
+    assert_eq!(output(source), "captured\n");
+}
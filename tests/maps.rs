@@ -0,0 +1,100 @@
+//! Map literals, indexing and the native map methods.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn builds_and_indexes_maps() {
+    let source = "
+    var m = {\"a\": 1, 2: \"two\", nil: true, false: 0};
+    print m;
+    print m[\"a\"];
+    print m[2];
+    m[\"a\"] = 10;
+    m[3] = 3;
+    print m;
+    print {};
+    ";
+    assert_eq!(
+        output(source),
+        "{a: 1, 2: two, nil: true, false: 0}\n1\ntwo\n\
+         {a: 10, 2: two, nil: true, false: 0, 3: 3}\n{}\n"
+    );
+}
+
+#[test]
+fn keys_equal_numbers_alike() {
+    assert_eq!(output("var m = {}; m[-0] = \"zero\"; print m[0]; print m.len();"), "zero\n1\n");
+    assert_eq!(output("var m = {1: \"a\"}; print m[2 / 2];"), "a\n");
+}
+
+#[test]
+fn rejects_nan_keys() {
+    for source in &["var m = {}; m[0 / 0] = 1;", "print {}[0 / 0];", "print {0 / 0: 1};"] {
+        let err = error(source);
+        assert_eq!(err.lines().next().unwrap(), "[line 1] Error: map keys cannot be NaN");
+    }
+}
+
+#[test]
+fn runs_native_methods() {
+    let source = "
+    var m = {\"a\": 1, \"b\": 2, \"c\": 3};
+    print m.len();
+    print m.has(\"a\");
+    print m.has(\"z\");
+    print m.remove(\"a\");
+    print m.remove(\"a\");
+    print m.keys();
+    print m.values();
+    print m.entries();
+    ";
+    assert_eq!(output(source), "3\ntrue\nfalse\n1\nnil\n[b, c]\n[2, 3]\n[[b, 2], [c, 3]]\n");
+}
+
+#[test]
+fn keeps_insertion_order_across_removals() {
+    let source = "
+    var m = {};
+    for (var i = 0; i < 10; i = i + 1) m[i] = i;
+    for (var i = 0; i < 8; i = i + 1) m.remove(i);
+    m[0] = \"back\";
+    m[9] = \"nine\";
+    print m;
+    print m[8];
+    print m.len();
+    ";
+    assert_eq!(output(source), "{8: 8, 9: nine, 0: back}\n8\n3\n");
+}
+
+#[test]
+fn iterates_over_snapshots() {
+    // `keys` returns a new list, so removing entries while walking it is fine.
+    let source = "
+    var m = {\"a\": 1, \"b\": 2, \"c\": 3};
+    var keys = m.keys();
+    for (var i = 0; i < keys.len(); i = i + 1) {
+        m.remove(keys[i]);
+        m[\"new\" + keys[i]] = i;
+    }
+    print keys;
+    print m;
+    ";
+    assert_eq!(output(source), "[a, b, c]\n{newa: 0, newb: 1, newc: 2}\n");
+}
+
+#[test]
+fn rejects_bad_keys() {
+    let cases = [
+        ("print {}[\"x\"];", "undefined key: x"),
+        ("print {[1, 2, 3]: 2};", "map keys must be strings, numbers, booleans or nil, got list"),
+        ("print {{\"a\": 1}: 2};", "map keys must be strings, numbers, booleans or nil, got map"),
+        ("print {}.nope;", "undefined map method: nope"),
+    ];
+    for (source, message) in &cases {
+        let err = error(source);
+        let first = err.lines().next().unwrap();
+        assert_eq!(first, format!("[line 1] Error: {}", message), "in {:?}", source);
+    }
+}
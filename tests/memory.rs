@@ -0,0 +1,101 @@
+//! Memory accounting per interpreter and the `max_memory` limit.
+
+mod common;
+
+use rlox::{
+    Backend,
+    Interpreter,
+};
+
+use common::*;
+
+/// A new interpreter for `backend` whose scripts may use `headroom` bytes
+/// on top of what the prelude takes.
+fn capped(backend: Backend, headroom: usize) -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    interp.limits.max_memory = Some(interp.memory_usage().total() + headroom);
+    interp
+}
+
+/// The first line of the error `source` stops with on `interp`.
+fn stopped(interp: &mut Interpreter, source: &str) -> String {
+    let (_, err) = run_in(interp, source);
+    let err = err.unwrap_or_else(|| panic!("no error in:\n{}", source));
+    err.lines().next().unwrap().to_string()
+}
+
+#[test]
+fn counts_each_interpreters_memory_apart() {
+    for &backend in &BACKENDS {
+        let mut big = Interpreter::new();
+        big.backend = backend;
+        let mut small = capped(backend, 100_000);
+        let before = small.memory_usage();
+
+        let source = "var s = \"x\"; for (var i = 0; i < 20; i = i + 1) s = s + s;";
+        assert_eq!(run_in(&mut big, source), (String::new(), None));
+        assert!(big.memory_usage().total() > 1 << 20, "{:?}", big.memory_usage());
+        assert_eq!(small.memory_usage(), before, "on {:?}", backend);
+
+        let source = "var l = []; for (var i = 0; i < 100; i = i + 1) l.push(i); print l.len();";
+        assert_eq!(run_in(&mut small, source), ("100\n".into(), None), "on {:?}", backend);
+    }
+}
+
+#[test]
+fn stops_growing_strings() {
+    for &backend in &BACKENDS {
+        let mut interp = capped(backend, 100_000);
+        let limit = interp.limits.max_memory.unwrap();
+        let err = stopped(&mut interp, "var s = \"x\";\nwhile (true) s = s + s;");
+        let expected = format!("out of memory: scripts may use at most {} bytes", limit);
+        assert_eq!(err, format!("[line 2] Error: {}", expected), "on {:?}", backend);
+    }
+}
+
+#[test]
+fn stops_growing_lists() {
+    for &backend in &BACKENDS {
+        let mut interp = capped(backend, 100_000);
+        let err = stopped(&mut interp, "var l = [];\nwhile (true) l.push(\"abcdefgh\");");
+        assert!(err.starts_with("[line 2] Error: out of memory"), "on {:?}: {}", backend, err);
+    }
+}
+
+#[test]
+fn frees_memory_once_released() {
+    for &backend in &BACKENDS {
+        let mut interp = capped(backend, 100_000);
+        let source = "
+        fun grow() {
+            var s = \"x\";
+            for (var i = 0; i < 12; i = i + 1) s = s + s;
+            return s.len();
+        }
+        for (var i = 0; i < 100; i = i + 1) grow();
+        print grow();
+        ";
+        assert_eq!(run_in(&mut interp, source), ("4096\n".into(), None), "on {:?}", backend);
+    }
+}
+
+#[test]
+fn raises_catchable_errors() {
+    let source = "
+    try {
+        var s = \"x\";
+        while (true) s = s + s;
+    } catch (e) {
+        print isInstance(e, RuntimeError);
+        print e.message.startsWith(\"out of memory\");
+    }
+    fun after() { return \"still running\"; }
+    print after();
+    ";
+    for &backend in &BACKENDS {
+        let mut interp = capped(backend, 100_000);
+        let expected = "true\ntrue\nstill running\n";
+        assert_eq!(run_in(&mut interp, source), (expected.into(), None), "on {:?}", backend);
+    }
+}
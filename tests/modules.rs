@@ -0,0 +1,127 @@
+//! `import`: loading, caching and the errors imported files stop with.
+
+mod common;
+
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use rlox::{
+    Capabilities,
+    Interpreter,
+    LoxError,
+};
+
+use common::*;
+
+/// A fresh directory holding `files`, given as names and contents.
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rlox-modules-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in files {
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    dir
+}
+
+/// An import of `name` in `dir`, as a statement.
+fn import(dir: &Path, name: &str, alias: &str) -> String {
+    format!("import \"{}\" as {};", dir.join(name).display(), alias)
+}
+
+#[test]
+fn imports_module_members() {
+    let dir = write_files("members", &[
+        ("util.lox", "var greeting = \"hi\"; fun twice(x) { return x * 2; }"),
+        ("main.lox", "import \"util.lox\" as util; var doubled = util.twice(21);"),
+    ]);
+    let source = format!(
+        "{} print util.greeting; print util.twice(2); {} print main.doubled;",
+        import(&dir, "util.lox", "util"),
+        import(&dir, "main.lox", "main"),
+    );
+    assert_eq!(output(&source), "hi\n4\n42\n");
+}
+
+#[test]
+fn runs_each_module_once() {
+    let dir = write_files("once", &[("loud.lox", "print \"loading\";")]);
+    let source = format!(
+        "{} {} print a == b;",
+        import(&dir, "loud.lox", "a"),
+        import(&dir, "loud.lox", "b"),
+    );
+    assert_eq!(output(&source), "loading\ntrue\n");
+}
+
+#[test]
+fn rejects_cyclic_imports() {
+    let dir = write_files("cycle", &[
+        ("a.lox", "import \"b.lox\" as b;"),
+        ("b.lox", "import \"a.lox\" as a;"),
+    ]);
+    let err = error(&import(&dir, "a.lox", "a"));
+    assert!(err.contains("cyclic import: a.lox -> b.lox -> a.lox"), "{}", err);
+}
+
+#[test]
+fn reports_every_error_in_an_imported_file() {
+    let dir = write_files("errors", &[("bad.lox", "var x = ;\nclass A < A {}\n")]);
+    let mut interp = Interpreter::new();
+    let errors = interp.eval(&format!("\n{}", import(&dir, "bad.lox", "bad"))).unwrap_err();
+
+    let (path, imported) = match &errors.errors()[0] {
+        LoxError::Import(location, path, imported) => {
+            assert_eq!(location.span.line, 2);
+            (path, imported)
+        },
+        err => panic!("not an import error: {}", err),
+    };
+    assert_eq!(path, &dir.join("bad.lox").display().to_string());
+    assert_eq!(errors.errors()[0].code(), "E0008");
+    let codes: Vec<_> = imported.errors().iter().map(|err| err.code()).collect();
+    assert_eq!(codes, ["E0002", "E0014"]);
+    let bad = dir.join("bad.lox").display().to_string();
+    for err in imported.errors() {
+        assert_eq!(err.location().unwrap().file.as_ref(), Some(&bad));
+    }
+
+    let rendered = errors.to_string();
+    assert!(rendered.contains(&format!("{}:1:9", bad)), "{}", rendered);
+    assert!(rendered.contains("class A < A {}"), "{}", rendered);
+}
+
+#[test]
+fn catches_import_errors() {
+    let dir = write_files("catch", &[("bad.lox", "print (;")]);
+    let source = format!(
+        "try {{ {} }} catch (e) {{ print e.message; }}",
+        import(&dir, "bad.lox", "bad")
+    );
+    let expected = format!("could not import {}\n", dir.join("bad.lox").display());
+    assert_eq!(output(&source), expected);
+}
+
+#[test]
+fn locates_runtime_errors_in_the_imported_file() {
+    let dir = write_files("runtime", &[("fails.lox", "\n\nprint nil + 1;")]);
+    let mut interp = Interpreter::new();
+    let errors = interp.eval(&import(&dir, "fails.lox", "fails")).unwrap_err();
+    let location = errors.errors()[0].location().unwrap();
+    assert_eq!(location.file, Some(dir.join("fails.lox").display().to_string()));
+    assert_eq!(location.span.line, 3);
+}
+
+#[test]
+fn needs_the_filesystem() {
+    let dir = write_files("disabled", &[("util.lox", "")]);
+    let mut interp = Interpreter::new();
+    interp.capabilities = Capabilities::none();
+    let (_, err) = run_in(&mut interp, &import(&dir, "util.lox", "util"));
+    assert_eq!(err.unwrap(), "[line 1] Error: import is disabled");
+}
@@ -0,0 +1,83 @@
+//! Rust functions registered as natives with `Interpreter::register`.
+
+mod common;
+
+use rlox::{
+    Backend,
+    Interpreter,
+};
+
+use common::*;
+
+/// An interpreter for `backend` with a few natives registered.
+fn with_natives(backend: Backend) -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    interp.register("longer", |n: f64, s: String| -> Result<bool, String> {
+        if n < 0.0 {
+            return Err(format!("negative length {}", n));
+        }
+        Ok(s.len() as f64 > n)
+    });
+    interp.register("greet", |name: Option<String>| {
+        format!("hello, {}", name.unwrap_or_else(|| "stranger".into()))
+    });
+    interp.register("answer", || 42.0);
+    interp
+}
+
+/// Runs `source` with the natives above on both backends, checking that
+/// they agree.
+fn run_natives(source: &str) -> (String, Option<String>) {
+    let tree_walk = run_in(&mut with_natives(Backend::TreeWalk), source);
+    let bytecode = run_in(&mut with_natives(Backend::Bytecode), source);
+    assert_eq!(tree_walk, bytecode, "the backends disagree on:\n{}", source);
+    tree_walk
+}
+
+#[test]
+fn converts_arguments_and_results() {
+    let source = "
+    print longer(2, \"abc\");
+    print longer(5, \"abc\");
+    print greet(\"ada\");
+    print greet(nil);
+    print answer();
+    ";
+    let expected = "true\nfalse\nhello, ada\nhello, stranger\n42\n";
+    assert_eq!(run_natives(source), (expected.into(), None));
+}
+
+#[test]
+fn reports_bad_arguments_at_the_call_site() {
+    let cases = [
+        ("\nlonger(\"2\", \"abc\");", "longer() expects a number for argument 1, got string"),
+        ("\nlonger(2, nil);", "longer() expects a string for argument 2, got nil"),
+        ("\ngreet(1);", "greet() expects a string or nil for argument 1, got number"),
+        ("\nlonger(1);", "expected 2 arguments but got 1"),
+        ("\nanswer(1);", "expected 0 arguments but got 1"),
+    ];
+    for (source, message) in &cases {
+        let err = run_natives(source).1.unwrap();
+        let first = err.lines().next().unwrap();
+        assert_eq!(first, format!("[line 2] Error: {}", message), "in {:?}", source);
+    }
+}
+
+#[test]
+fn raises_returned_errors() {
+    let err = run_natives("\n\nlonger(-1, \"\");").1.unwrap();
+    assert_eq!(err.lines().next().unwrap(), "[line 3] Error: negative length -1");
+}
+
+#[test]
+fn raises_catchable_errors() {
+    let source = "
+    try { longer(\"1\", \"\"); } catch (e) { print isInstance(e, TypeError); }
+    try { longer(1); } catch (e) { print isInstance(e, ArityError); }
+    try { longer(-1, \"\"); } catch (e) { print isInstance(e, TypeError); }
+    try { longer(-1, \"\"); } catch (e) { print e.message; }
+    ";
+    let expected = "true\ntrue\nfalse\nnegative length -1\n";
+    assert_eq!(run_natives(source), (expected.into(), None));
+}
@@ -0,0 +1,53 @@
+//! How numbers are printed under each `NumberFormat`.
+
+mod common;
+
+use rlox::{
+    Interpreter,
+    NumberFormat,
+    Value,
+};
+
+use common::*;
+
+const SOURCE: &str = "
+print 1000000000000000000000;
+print 0.5;
+print str(100);
+print \"n = \" + str(2.5);
+print [0.0001];
+";
+
+/// What `SOURCE` prints on an interpreter using `format`.
+fn printed(format: NumberFormat) -> String {
+    let mut interp = Interpreter::new();
+    interp.number_format = format;
+    let (output, err) = run_in(&mut interp, SOURCE);
+    assert_eq!(err, None);
+    output
+}
+
+#[test]
+fn prints_in_each_format() {
+    assert_eq!(
+        printed(NumberFormat::Shortest),
+        "1000000000000000000000\n0.5\n100\nn = 2.5\n[0.0001]\n"
+    );
+    assert_eq!(printed(NumberFormat::Reference), "1.0E21\n0.5\n100\nn = 2.5\n[1.0E-4]\n");
+    assert_eq!(
+        printed(NumberFormat::Fixed(2)),
+        "1000000000000000000000.00\n0.50\n100.00\nn = 2.50\n[0.00]\n"
+    );
+}
+
+#[test]
+fn keeps_each_interpreters_format() {
+    let mut reference = Interpreter::new();
+    reference.number_format = NumberFormat::Reference;
+    let mut shortest = Interpreter::new();
+    let (output, _) = run_in(&mut reference, "print 1000000000000000000000;");
+    assert_eq!(output, "1.0E21\n");
+    let (output, _) = run_in(&mut shortest, "print 1000000000000000000000;");
+    assert_eq!(output, "1000000000000000000000\n");
+    assert_eq!(Value::from(1e21).to_string(), "1000000000000000000000");
+}
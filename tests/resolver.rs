@@ -0,0 +1,226 @@
+//! Conformance tests for the static errors the resolver reports: their
+//! codes, exact messages and the spans editors highlight.
+
+use std::collections::HashSet;
+
+use rlox::{
+    parse,
+    Lints,
+};
+
+/// A program with exactly one static error.
+struct Case {
+    source:  &'static str,
+    code:    &'static str,
+    message: &'static str,
+    /// The line and column the error points at.
+    at:      (usize, usize),
+    /// The source text under the error's span.
+    text:    &'static str,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        source:  "return;",
+        code:    "E0009",
+        message: "[line 1] Error at \"return\": cannot return from top-level code",
+        at:      (1, 1),
+        text:    "return",
+    },
+    Case {
+        source:  "if (true) {\n  return 1;\n}",
+        code:    "E0009",
+        message: "[line 2] Error at \"return\": cannot return from top-level code",
+        at:      (2, 3),
+        text:    "return",
+    },
+    Case {
+        source:  "class A {\n  init() {\n    return 1;\n  }\n}",
+        code:    "E0010",
+        message: "[line 3] Error at \"return\": cannot return a value from an initializer",
+        at:      (3, 5),
+        text:    "return",
+    },
+    Case {
+        source:  "print this;",
+        code:    "E0011",
+        message: "[line 1] Error at \"this\": cannot use 'this' outside of a class",
+        at:      (1, 7),
+        text:    "this",
+    },
+    Case {
+        source:  "fun f() {\n  return this;\n}",
+        code:    "E0011",
+        message: "[line 2] Error at \"this\": cannot use 'this' outside of a class",
+        at:      (2, 10),
+        text:    "this",
+    },
+    Case {
+        source:  "fun f() {\n  super.m();\n}",
+        code:    "E0012",
+        message: "[line 2] Error at \"super\": cannot use 'super' outside of a class",
+        at:      (2, 3),
+        text:    "super",
+    },
+    Case {
+        source:  "class A {\n  m() {\n    super.m();\n  }\n}",
+        code:    "E0013",
+        message: "[line 3] Error at \"super\": cannot use 'super' in a class with no superclass",
+        at:      (3, 5),
+        text:    "super",
+    },
+    Case {
+        source:  "class A < A {}",
+        code:    "E0014",
+        message: "[line 1] Error at \"A\": a class cannot inherit from itself",
+        at:      (1, 11),
+        text:    "A",
+    },
+    Case {
+        source:  "{\n  class A < A {}\n}",
+        code:    "E0014",
+        message: "[line 2] Error at \"A\": a class cannot inherit from itself",
+        at:      (2, 13),
+        text:    "A",
+    },
+    Case {
+        source:  "{\n  var a = 1;\n  var a = 2;\n}",
+        code:    "E0015",
+        message: "[line 3] Error at \"a\": already a variable with this name in this scope",
+        at:      (3, 7),
+        text:    "a",
+    },
+    Case {
+        source:  "fun f(a, a) {}",
+        code:    "E0015",
+        message: "[line 1] Error at \"a\": already a variable with this name in this scope",
+        at:      (1, 10),
+        text:    "a",
+    },
+    Case {
+        source:  "{\n  var a = a;\n}",
+        code:    "E0016",
+        message: "[line 2] Error at \"a\": cannot read local variable in its own initializer",
+        at:      (2, 11),
+        text:    "a",
+    },
+    Case {
+        source:  "break;",
+        code:    "E0017",
+        message: "[line 1] Error at \"break\": cannot use 'break' outside of a loop",
+        at:      (1, 1),
+        text:    "break",
+    },
+    Case {
+        source:  "while (true) {\n  fun f() {\n    continue;\n  }\n}",
+        code:    "E0018",
+        message: "[line 3] Error at \"continue\": cannot use 'continue' outside of a loop",
+        at:      (3, 5),
+        text:    "continue",
+    },
+];
+
+#[test]
+fn reports_each_error_precisely() {
+    for case in CASES {
+        let errors = match parse(None, case.source, Lints::none()) {
+            Ok(_) => panic!("no error in {:?}", case.source),
+            Err(errors) => errors,
+        };
+        assert_eq!(errors.errors().len(), 1, "in {:?}: {}", case.source, errors);
+
+        let err = &errors.errors()[0];
+        assert_eq!(err.code(), case.code, "in {:?}", case.source);
+        assert_eq!(err.to_string(), case.message, "in {:?}", case.source);
+        let span = err.span().unwrap();
+        assert_eq!((span.line, span.column), case.at, "in {:?}", case.source);
+        assert_eq!(&case.source[span.start..span.end], case.text, "in {:?}", case.source);
+    }
+}
+
+#[test]
+fn gives_each_error_its_own_code() {
+    // The message without its location.
+    let description = |message: &'static str| message.split_once(": ").unwrap().1;
+    let kinds: HashSet<_> =
+        CASES.iter().map(|case| (case.code, description(case.message))).collect();
+    let codes: HashSet<_> = kinds.iter().map(|(code, _)| code).collect();
+    let descriptions: HashSet<_> = kinds.iter().map(|(_, description)| description).collect();
+    assert_eq!(kinds.len(), 10);
+    assert_eq!(codes.len(), kinds.len());
+    assert_eq!(descriptions.len(), kinds.len());
+}
+
+#[test]
+fn reports_every_error_in_a_file() {
+    let source = "
+        return;
+        class A < A {}
+        print this;
+        fun f(a, a) {
+            break;
+        }
+    ";
+    let errors = parse(None, source, Lints::none()).err().unwrap();
+    let codes: Vec<_> = errors.errors().iter().map(|err| err.code()).collect();
+    assert_eq!(codes, ["E0009", "E0014", "E0011", "E0015", "E0017"]);
+}
+
+#[test]
+fn accepts_valid_uses() {
+    let source = "
+        var a = 1;
+        {
+            var a = 2;
+            print a;
+        }
+        fun f(x) {
+            if (x) return x;
+            return;
+        }
+        class A {
+            init() {
+                this.x = 1;
+                return;
+            }
+        }
+        class B < A {
+            m() {
+                return super.init();
+            }
+        }
+        while (true) {
+            if (a) break;
+            continue;
+        }
+    ";
+    assert!(parse(None, source, Lints::none()).is_ok());
+}
+
+#[test]
+fn recovers_inside_blocks() {
+    // Recovering from an error mustn't swallow the brace closing its block,
+    // or the rest of the file would be parsed as part of that block.
+    let source = "
+        fun f() { return }
+        print f();
+        fun g() {
+            var = 1;
+            if (true) { print (; }
+            print this;
+        }
+        print 1 +;
+    ";
+    let errors = parse(None, source, Lints::none()).err().unwrap();
+    let messages: Vec<_> = errors.errors().iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "[line 2] Error at \"}\": expect expression",
+            "[line 5] Error at \"=\": expect variable name",
+            "[line 6] Error at \";\": expect expression",
+            "[line 9] Error at \";\": expect expression",
+            "[line 7] Error at \"this\": cannot use 'this' outside of a class",
+        ]
+    );
+}
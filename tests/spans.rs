@@ -0,0 +1,102 @@
+//! Checks that errors point at the right line, column and source text.
+
+mod common;
+
+use rlox::{
+    parse,
+    Interpreter,
+    Lints,
+    Span,
+};
+
+use common::*;
+
+/// The span of the first error found before `source` runs.
+fn static_span(source: &str) -> Span {
+    let errors = parse(None, source, Lints::none()).expect_err("no error");
+    errors.errors()[0].span().unwrap()
+}
+
+/// The span of the error `source` stops with when run with `backend`.
+fn runtime_span(backend: rlox::Backend, source: &str) -> Span {
+    let mut interp = Interpreter::new();
+    interp.backend = backend;
+    let errors = interp.eval(source).expect_err("no error");
+    errors.errors()[0].span().unwrap()
+}
+
+fn text(source: &str, span: Span) -> &str {
+    &source[span.start..span.end]
+}
+
+#[test]
+fn locates_scan_errors() {
+    let source = "var a = 1;\nvar b = @;";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (2, 9));
+    assert_eq!(text(source, span), "@");
+
+    let source = "print \"open";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (1, 7));
+    assert_eq!(text(source, span), "\"open");
+}
+
+#[test]
+fn locates_parse_errors() {
+    let source = "print 1;\n  print (2;";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (2, 11));
+    assert_eq!(text(source, span), ";");
+}
+
+#[test]
+fn counts_columns_in_characters() {
+    let source = "var s = \"héllo\"; print s + @;";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (1, 28));
+    assert_eq!(text(source, span), "@");
+}
+
+#[test]
+fn counts_columns_from_the_last_line_break() {
+    // Line breaks inside strings and comments start a new line, too.
+    let source = "var s = \"a\nbé\"; // x\n  var t = s + @;";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (3, 15));
+    assert_eq!(text(source, span), "@");
+
+    let source = "var s = \"a\nbé\"; @";
+    let span = static_span(source);
+    assert_eq!((span.line, span.column), (2, 6));
+}
+
+#[test]
+fn locates_runtime_errors() {
+    let source = "var a = 1;\nprint a + nil;";
+    for &backend in &BACKENDS {
+        let span = runtime_span(backend, source);
+        assert_eq!((span.line, span.column), (2, 7), "{:?}", backend);
+        assert_eq!(text(source, span), "a + nil", "{:?}", backend);
+    }
+
+    // Calls are located at their closing parenthesis.
+    let source = "fun f() {}\nf(\n  1);";
+    for &backend in &BACKENDS {
+        let span = runtime_span(backend, source);
+        assert_eq!((span.line, span.column), (3, 4), "{:?}", backend);
+        assert_eq!(text(source, span), ")", "{:?}", backend);
+    }
+}
+
+#[test]
+fn locates_unary_operand_errors() {
+    // Unary errors are located at their operator.
+    for &(source, column) in &[("print 1;\nprint -\"a\";", 7), ("print 1;\nprint !1 + -nil;", 12)] {
+        for &backend in &BACKENDS {
+            let span = runtime_span(backend, source);
+            assert_eq!((span.line, span.column), (2, column), "{:?}", backend);
+            assert_eq!(text(source, span), "-", "{:?}", backend);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! String indexing and the native string methods.
+
+mod common;
+
+use common::*;
+
+#[test]
+fn runs_native_methods() {
+    let source = "
+    print \"héllo\".len();
+    print \"hello world\".substring(6, 11);
+    print \"abc\".substring(1, 1);
+    print \"héllo\".indexOf(\"llo\");
+    print \"abc\".indexOf(\"z\");
+    print \"a,b,,c\".split(\",\");
+    print \"ab\".split(\"\");
+    print \"  hi  \".trim() + \"!\";
+    print \"   \".trim() + \"!\";
+    print \"Hé\".upper();
+    print \"Hé\".lower();
+    print \"aaa\".replace(\"a\", \"ba\");
+    print \"prefix\".startsWith(\"pre\");
+    print \"prefix\".endsWith(\"pre\");
+    print \"héy\".chars();
+    ";
+    assert_eq!(
+        output(source),
+        "5\nworld\n\n2\n-1\n[a, b, , c]\n[a, b]\nhi!\n!\nHÉ\nhé\nbababa\ntrue\nfalse\n[h, é, y]\n"
+    );
+}
+
+#[test]
+fn indexes_by_character() {
+    assert_eq!(output("var s = \"héllo\"; print s[1]; print s[0] + s[4];"), "é\nho\n");
+}
+
+#[test]
+fn binds_methods_to_their_string() {
+    let source = "
+    var s = \"abc\";
+    var upper = s.upper;
+    s = \"xyz\";
+    print upper();
+    print s;
+    ";
+    assert_eq!(output(source), "ABC\nxyz\n");
+}
+
+#[test]
+fn rejects_bad_indices_and_arguments() {
+    let cases = [
+        ("print \"abc\"[3];", "index 3 out of range for string of length 3"),
+        ("print \"abc\"[1.5];", "string index must be an integer, got 1.5"),
+        ("print \"abc\"[\"x\"];", "string index must be a number, got x"),
+        ("\"abc\".substring(2, 1);", "substring start 2 is after end 1"),
+        ("\"abc\".substring(0, 4);", "index 4 out of range for string of length 3"),
+        ("\"a\".replace(\"\", \"b\");", "cannot replace an empty string"),
+        ("\"a\".indexOf(1);", "indexOf() expects a string for argument 1, got number"),
+        ("\"a\".len(1);", "expected 0 arguments but got 1"),
+        ("\"a\".nope;", "undefined string method: nope"),
+    ];
+    for (source, message) in &cases {
+        let err = error(source);
+        let first = err.lines().next().unwrap();
+        assert_eq!(first, format!("[line 1] Error: {}", message), "in {:?}", source);
+    }
+}
+
+#[test]
+fn raises_catchable_errors() {
+    let source = "
+    try { \"abc\"[3]; } catch (e) { print isInstance(e, IndexError); }
+    try { \"abc\"[1.5]; } catch (e) { print isInstance(e, TypeError); }
+    try { \"abc\".nope; } catch (e) { print isInstance(e, NameError); }
+    ";
+    assert_eq!(output(source), "true\ntrue\ntrue\n");
+}
@@ -0,0 +1,97 @@
+//! The stack traces attached to uncaught runtime errors.
+
+mod common;
+
+use rlox::Interpreter;
+
+use common::*;
+
+#[test]
+fn traces_nested_calls() {
+    let source = "
+class A {
+  m() { return nil + 1; }
+}
+fun f() {
+  return A().m();
+}
+f();";
+    assert_eq!(
+        error(source),
+        "\
+[line 3] Error: requires two numbers or two strings
+[line 3] in m()
+[line 6] in f()
+[line 8] in script"
+    );
+}
+
+#[test]
+fn marks_native_frames() {
+    let source = "fun f() {\n  return [1].slice(0, 5);\n}\nf();";
+    assert_eq!(
+        error(source),
+        "\
+[line 2] Error: index 5 out of range for list of length 1
+[native] in slice()
+[line 2] in f()
+[line 4] in script"
+    );
+}
+
+#[test]
+fn summarizes_deep_recursion() {
+    let source = "
+fun r(n) {
+  if (n == 0) return nil + 1;
+  return r(n - 1);
+}
+r(20);";
+    assert_eq!(
+        error(source),
+        "\
+[line 3] Error: requires two numbers or two strings
+[line 3] in r()
+[line 4] in r()
+[line 4] in r()
+[line 4] in r()
+[previous line repeated 17 more times]
+[line 6] in script"
+    );
+}
+
+#[test]
+fn leaves_top_level_errors_without_a_trace() {
+    assert_eq!(error("print nil + 1;"), "[line 1] Error: requires two numbers or two strings");
+}
+
+#[test]
+fn forgets_frames_unwound_by_a_caught_error() {
+    let source = "
+fun f() { throw \"no\"; }
+try { f(); } catch (e) {}
+fun g() { return nil + 1; }
+g();";
+    assert_eq!(
+        error(source),
+        "\
+[line 4] Error: requires two numbers or two strings
+[line 4] in g()
+[line 5] in script"
+    );
+}
+
+#[test]
+fn marks_calls_from_host_code() {
+    let mut interp = Interpreter::new();
+    interp.eval("fun f() {\n  return nil + 1;\n}").unwrap();
+    let f = interp.global("f").unwrap();
+    let err = interp.call_function(&f, vec![]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "\
+[line 2] Error: requires two numbers or two strings
+[line 2] in f()
+[host]"
+    );
+}